@@ -0,0 +1,37 @@
+//! cargo +nightly fuzz run get_buffers_range
+#![no_main]
+
+use io_uring_rb::buffer_source::BufferSource;
+use io_uring_rb::mock::MockRing;
+use libfuzzer_sys::fuzz_target;
+
+// small pool so most inputs wrap the ring
+const BUFFER_SIZE: u32 = 8;
+const RING_SIZE: u16 = 4;
+const POOL_BYTES: usize = BUFFER_SIZE as usize * RING_SIZE as usize;
+
+fuzz_target!(|input: (u8, Vec<u8>)| {
+    let (skip, mut payload) = input;
+    payload.truncate(POOL_BYTES);
+    let mock = MockRing::<BUFFER_SIZE, RING_SIZE>::new();
+
+    // moves the next bid the "kernel" will use
+    for _ in 0..skip % RING_SIZE as u8 {
+        mock.inject(&[0xff; BUFFER_SIZE as usize]);
+    }
+    let (bid, len) = mock.inject(&payload);
+
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    assert_eq!(range.len(), len);
+
+    let (first, second) = range.as_parts();
+    let wraps = bid as usize * BUFFER_SIZE as usize + len > POOL_BYTES;
+    assert_eq!(second.is_some(), wraps);
+    assert_eq!([first, second.unwrap_or_default()].concat(), payload);
+
+    mock.recycle_buffers_range(&range);
+    let expected: Vec<u16> = (0..len.div_ceil(BUFFER_SIZE as usize))
+        .map(|i| ((bid as usize + i) % RING_SIZE as usize) as u16)
+        .collect();
+    assert_eq!(mock.recycled_bids(), expected);
+});
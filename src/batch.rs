@@ -0,0 +1,77 @@
+use std::ops::Range;
+
+use io_uring::{SubmissionQueue, cqueue, opcode, squeue, types::Fd};
+
+use crate::{
+    RingBuffer,
+    buffer::Buffer,
+    recv_result::{RecvResult, classify_recv},
+};
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    /// pushes up to `count` single-shot recvs on `fd`, each selecting one buffer of this group,
+    /// with user_data `first_user_data`, `first_user_data + 1`...
+    /// returns the user_data of the SQEs actually pushed, fewer than `count` if the queue filled up.
+    pub fn push_recv_batch(
+        &self,
+        sq: &mut SubmissionQueue<'_>,
+        fd: Fd,
+        count: u32,
+        first_user_data: u64,
+    ) -> Range<u64> {
+        let mut user_data = first_user_data;
+        while user_data - first_user_data < count as u64 {
+            let entry = self.recv_entry(fd).user_data(user_data);
+            // no buffer in the SQE, the kernel picks one from the group
+            if unsafe { sq.push(&entry) }.is_err() {
+                break;
+            }
+            user_data += 1;
+        }
+        first_user_data..user_data
+    }
+
+    /// a PollAdd for POLLIN linked to a single-shot recv selecting its buffer from this group,
+    /// push both in order. the recv only starts once the fd is readable, if the poll fails the
+    /// recv completes with ECANCELED. each SQE gets its own user_data to tell their CQEs apart.
+    pub fn poll_then_recv(
+        &self,
+        fd: Fd,
+        poll_user_data: u64,
+        recv_user_data: u64,
+    ) -> [squeue::Entry; 2] {
+        [
+            opcode::PollAdd::new(fd, libc::POLLIN as _)
+                .build()
+                .flags(squeue::Flags::IO_LINK)
+                .user_data(poll_user_data),
+            self.recv_entry(fd).user_data(recv_user_data),
+        ]
+    }
+
+    fn recv_entry(&self, fd: Fd) -> squeue::Entry {
+        opcode::Recv::new(fd, std::ptr::null_mut(), BUFFER_SIZE)
+            .buf_group(self.id)
+            .build()
+            .flags(squeue::Flags::BUFFER_SELECT)
+    }
+
+    /// gathers the completions of a `push_recv_batch`, CQEs with another user_data are skipped.
+    /// yields each user_data with its classified result and the buffer it selected.
+    pub fn collect_recv_batch<'a>(
+        &'a self,
+        cq: impl IntoIterator<Item = cqueue::Entry> + 'a,
+        user_data: Range<u64>,
+    ) -> impl Iterator<Item = (u64, RecvResult, Option<Buffer<BUFFER_SIZE>>)> + 'a {
+        cq.into_iter()
+            .filter(move |cqe| user_data.contains(&cqe.user_data()))
+            .map(|cqe| {
+                let result = classify_recv(cqe.result(), cqe.flags());
+                let buffer = match result {
+                    RecvResult::Data { bid, len, .. } => self.get_buffer(bid, len),
+                    _ => None,
+                };
+                (cqe.user_data(), result, buffer)
+            })
+    }
+}
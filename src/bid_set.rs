@@ -0,0 +1,66 @@
+use std::cell::Cell;
+
+/// fixed-size bitset of buffer ids, mutable through a shared reference
+pub(crate) struct BidSet {
+    words: Box<[Cell<u64>]>,
+}
+
+impl BidSet {
+    pub(crate) fn new(len: u16) -> Self {
+        Self {
+            words: (0..(len as usize).div_ceil(64))
+                .map(|_| Cell::new(0))
+                .collect(),
+        }
+    }
+
+    fn locate(bid: u16) -> (usize, u64) {
+        (bid as usize / 64, 1 << (bid % 64))
+    }
+
+    /// returns false if bid was already in the set
+    pub(crate) fn insert(&self, bid: u16) -> bool {
+        let (word, mask) = Self::locate(bid);
+        let old = self.words[word].get();
+        self.words[word].set(old | mask);
+        old & mask == 0
+    }
+
+    /// returns false if bid wasn't in the set
+    pub(crate) fn remove(&self, bid: u16) -> bool {
+        let (word, mask) = Self::locate(bid);
+        let old = self.words[word].get();
+        self.words[word].set(old & !mask);
+        old & mask != 0
+    }
+
+    pub(crate) fn contains(&self, bid: u16) -> bool {
+        let (word, mask) = Self::locate(bid);
+        self.words[word].get() & mask != 0
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.words.iter().all(|word| word.get() == 0)
+    }
+
+    /// bids in the set, in increasing order
+    pub(crate) fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            let mut bits = word.get();
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let bit = bits.trailing_zeros();
+                bits &= bits - 1;
+                Some((i * 64) as u16 + bit as u16)
+            })
+        })
+    }
+
+    pub(crate) fn clear(&self) {
+        for word in self.words.iter() {
+            word.set(0);
+        }
+    }
+}
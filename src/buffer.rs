@@ -1,5 +1,12 @@
 use core::slice;
-use std::{marker::PhantomData, ptr::NonNull};
+use std::{
+    io::{self, Write},
+    marker::PhantomData,
+    ops::Range,
+    ptr::NonNull,
+};
+
+use crate::hexdump::HexDump;
 
 /// this buffer represents an immutable slice in a buffer, recycle it when you are done.
 /// not automatically returned on Drop.
@@ -15,6 +22,27 @@ impl<const SIZE: u32> Buffer<SIZE> {
     pub fn bid(&self) -> u16 {
         self.bid
     }
+
+    /// hexdump of the data, capped for big buffers
+    pub fn hexdump(&self) -> HexDump<'_> {
+        HexDump::new(self.as_ref(), None)
+    }
+
+    /// the data split in two at `mid`, None if `mid` is past the end of the data
+    pub fn split_at(&self, mid: usize) -> Option<(&[u8], &[u8])> {
+        self.as_ref().split_at_checked(mid)
+    }
+
+    /// the bytes of `range`, e.g. the payload after a fixed-size header.
+    /// None if the range is past the end of the data
+    pub fn subslice(&self, range: Range<usize>) -> Option<&[u8]> {
+        self.as_ref().get(range)
+    }
+
+    /// mutable version of split_at
+    pub fn split_at_mut(&mut self, mid: usize) -> Option<(&mut [u8], &mut [u8])> {
+        self.as_mut().split_at_mut_checked(mid)
+    }
 }
 
 impl<const SIZE: u32> AsRef<[u8]> for Buffer<SIZE> {
@@ -28,3 +56,71 @@ impl<const SIZE: u32> AsMut<[u8]> for Buffer<SIZE> {
         unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
     }
 }
+
+impl<const SIZE: u32> PartialEq<[u8]> for Buffer<SIZE> {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_ref() == other
+    }
+}
+
+impl<const SIZE: u32> PartialEq<&[u8]> for Buffer<SIZE> {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.as_ref() == *other
+    }
+}
+
+/// a buffer checked out of the pool for sending, never seen by the kernel as a recv buffer.
+/// release it with `RingBuffer::release_send_buffer` once the send completed.
+/// implements io::Write from the start of the buffer, the bytes written extend the length sent.
+#[derive(Debug)]
+pub struct BufferMut<const SIZE: u32> {
+    pub(crate) ptr: NonNull<u8>,
+    pub(crate) len: usize,
+    /// io::Write cursor
+    pub(crate) pos: usize,
+    pub(crate) bid: u16,
+    pub(crate) _not_send_sync: PhantomData<*const ()>,
+}
+
+impl<const SIZE: u32> BufferMut<SIZE> {
+    pub fn bid(&self) -> u16 {
+        self.bid
+    }
+}
+
+/// writes at the cursor up to SIZE bytes, a full buffer accepts 0 bytes so write_all fails
+/// with WriteZero instead of overflowing
+impl<const SIZE: u32> Write for BufferMut<SIZE> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(SIZE as usize - self.pos);
+        unsafe {
+            std::ptr::copy_nonoverlapping(buf.as_ptr(), self.ptr.as_ptr().add(self.pos), count);
+        }
+        self.pos += count;
+        self.len = self.len.max(self.pos);
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<const SIZE: u32> AsRef<[u8]> for BufferMut<SIZE> {
+    fn as_ref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<const SIZE: u32> AsMut<[u8]> for BufferMut<SIZE> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+/// copies the data out, the buffer still has to be recycled
+impl<const SIZE: u32> From<&Buffer<SIZE>> for Vec<u8> {
+    fn from(buffer: &Buffer<SIZE>) -> Self {
+        buffer.as_ref().to_vec()
+    }
+}
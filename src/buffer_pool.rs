@@ -1,25 +1,87 @@
-use std::ptr::{NonNull, null_mut};
+use std::{
+    ops::Range,
+    ptr::{NonNull, null_mut},
+};
 
-use rustix::mm::{MapFlags, ProtFlags, mmap_anonymous};
+use rustix::mm::{Advice, MprotectFlags, ProtFlags, madvise, mmap_anonymous, mprotect};
 
-use crate::BufferId;
+use crate::{BufferId, builder::MapOptions, error::RingBufferError, mlock_region};
 
 pub struct BufferPool<const BUFFER_SIZE: u32, const RING_SIZE: u16> {
     ptr: *mut u8,
+    locked: bool,
+    /// false when the pool is carved out of a mapping owned by someone else
+    owned: bool,
 }
 
 impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> BufferPool<BUFFER_SIZE, RING_SIZE> {
-    pub fn new() -> std::io::Result<Self> {
-        let total_size = (BUFFER_SIZE * RING_SIZE as u32) as usize;
-        let ptr = unsafe {
+    pub(crate) const TOTAL_SIZE: usize = (BUFFER_SIZE * RING_SIZE as u32) as usize;
+
+    pub fn new(lock: bool, options: &MapOptions) -> std::io::Result<Self> {
+        let total_size = Self::TOTAL_SIZE;
+        let guard = Self::guard_len();
+        let base = unsafe {
             mmap_anonymous(
                 null_mut(),
-                total_size,
-                ProtFlags::READ | ProtFlags::WRITE,
-                MapFlags::PRIVATE | MapFlags::POPULATE,
-            )?
+                Self::map_len(),
+                options.pool_prot,
+                options.map_flags(true),
+            )
+        }
+        .map_err(|e| RingBufferError::PoolMmap {
+            bytes: Self::map_len(),
+            source: e.into(),
+        })?;
+        let ptr = unsafe { base.cast::<u8>().add(guard) };
+        let mut pool = Self {
+            ptr,
+            locked: false,
+            owned: true,
         };
-        Ok(Self { ptr: ptr.cast() })
+        if guard != 0 {
+            unsafe {
+                mprotect(base, guard, MprotectFlags::empty())?;
+                mprotect(
+                    ptr.add(total_size.next_multiple_of(guard)).cast(),
+                    guard,
+                    MprotectFlags::empty(),
+                )?;
+            }
+        }
+        if lock {
+            unsafe { mlock_region(ptr.cast(), total_size)? };
+            pool.locked = true;
+        }
+        Ok(pool)
+    }
+
+    /// size of the PROT_NONE guard page mapped on each side of the pool
+    #[cfg(feature = "guard-pages")]
+    fn guard_len() -> usize {
+        rustix::param::page_size()
+    }
+
+    #[cfg(not(feature = "guard-pages"))]
+    fn guard_len() -> usize {
+        0
+    }
+
+    /// size of the whole mapping, guard pages included
+    fn map_len() -> usize {
+        match Self::guard_len() {
+            0 => Self::TOTAL_SIZE,
+            guard => Self::TOTAL_SIZE.next_multiple_of(guard) + 2 * guard,
+        }
+    }
+
+    /// uses TOTAL_SIZE bytes at `ptr` without owning them, nothing is unmapped on drop.
+    /// `ptr` must stay mapped for as long as the pool lives.
+    pub(crate) unsafe fn from_raw(ptr: *mut u8) -> Self {
+        Self {
+            ptr,
+            locked: false,
+            owned: false,
+        }
     }
 
     /// Returns the pointer offset for a given buffer id
@@ -35,6 +97,42 @@ impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> BufferPool<BUFFER_SIZE, RING_
         NonNull::new(self.buffer_offset(bid))
     }
 
+    /// same as get without the bound check, `bid` must be below RING_SIZE
+    pub(crate) unsafe fn get_unchecked(&self, bid: u16) -> NonNull<u8> {
+        unsafe { NonNull::new_unchecked(self.buffer_offset(bid)) }
+    }
+
+    /// changes the protection of the whole pool, guard pages excluded
+    pub(crate) fn protect(&self, prot: ProtFlags) -> std::io::Result<()> {
+        let flags = MprotectFlags::from_bits_retain(prot.bits());
+        unsafe { mprotect(self.ptr.cast(), Self::TOTAL_SIZE, flags)? };
+        Ok(())
+    }
+
+    /// sets every byte of the pool to `byte`, the kernel must not own any buffer yet
+    pub(crate) fn fill(&self, byte: u8) {
+        unsafe { self.ptr.write_bytes(byte, Self::TOTAL_SIZE) };
+    }
+
+    /// forwards a madvise hint over the whole pool
+    pub(crate) fn advise(&self, advice: Advice) -> std::io::Result<()> {
+        unsafe { madvise(self.ptr.cast(), Self::TOTAL_SIZE, advice)? };
+        Ok(())
+    }
+
+    /// forwards a madvise hint over the buffers `bids`, widened to the pages holding them
+    pub(crate) fn advise_buffers(
+        &self,
+        bids: Range<BufferId>,
+        advice: Advice,
+    ) -> std::io::Result<()> {
+        let page_size = rustix::param::page_size();
+        let start = (bids.start as usize * BUFFER_SIZE as usize) / page_size * page_size;
+        let end = (bids.end as usize * BUFFER_SIZE as usize).next_multiple_of(page_size);
+        unsafe { madvise(self.ptr.add(start).cast(), end - start, advice)? };
+        Ok(())
+    }
+
     ///for building purpose
     pub(crate) fn ptr_for_bid(&self, bid: BufferId) -> *mut u8 {
         assert!(bid < RING_SIZE);
@@ -44,9 +142,15 @@ impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> BufferPool<BUFFER_SIZE, RING_
 
 impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> Drop for BufferPool<BUFFER_SIZE, RING_SIZE> {
     fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        let total_size = Self::TOTAL_SIZE;
         unsafe {
-            let _ =
-                rustix::mm::munmap(self.ptr.cast(), (BUFFER_SIZE * (RING_SIZE as u32)) as usize);
+            if self.locked {
+                let _ = rustix::mm::munlock(self.ptr.cast(), total_size);
+            }
+            let _ = rustix::mm::munmap(self.ptr.sub(Self::guard_len()).cast(), Self::map_len());
         }
     }
 }
@@ -0,0 +1,327 @@
+use core::slice;
+use std::{
+    borrow::Cow,
+    io::{self, IoSlice, Write},
+    marker::PhantomData,
+    ops::{Index, Range},
+    os::fd::AsFd,
+    ptr::NonNull,
+};
+
+use crate::hexdump::HexDump;
+
+/// one contiguous segment of a BufferRange
+#[derive(Debug)]
+pub(crate) struct BufferRangeInner {
+    pub(crate) ptr: NonNull<u8>,
+    pub(crate) len: usize,
+    /// length before any truncate, the buffers to recycle
+    pub(crate) covered: usize,
+}
+
+impl BufferRangeInner {
+    pub(crate) fn new(ptr: NonNull<u8>, len: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            covered: len,
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+/// data received across consecutive buffers (e.g. a bundle recv), recycle it when you are done.
+/// the buffers are contiguous in the pool, except when they wrap at the end of the ring:
+/// the range is then split in two segments, the second one starting at buffer 0.
+/// not automatically returned on Drop.
+#[derive(Debug)]
+pub struct BufferRange<const SIZE: u32> {
+    pub(crate) first_bid: u16,
+    pub(crate) first: BufferRangeInner,
+    pub(crate) second: Option<BufferRangeInner>,
+    pub(crate) _not_send_sync: PhantomData<*const ()>,
+}
+
+impl<const SIZE: u32> BufferRange<SIZE> {
+    /// both segments of the range, the second one is Some only if the range wrapped
+    /// (and wasn't truncated back into the first segment)
+    pub fn as_parts(&self) -> (&[u8], Option<&[u8]>) {
+        (
+            self.first.as_slice(),
+            self.second
+                .as_ref()
+                .filter(|s| s.len > 0)
+                .map(BufferRangeInner::as_slice),
+        )
+    }
+
+    /// shortens the view of the range to `new_len` bytes, no-op if it is already shorter.
+    /// recycling still returns every buffer the range covered before the trim.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len >= self.len() {
+            return;
+        }
+        match &mut self.second {
+            Some(second) if new_len > self.first.len => second.len = new_len - self.first.len,
+            _ => {
+                self.first.len = self.first.len.min(new_len);
+                if let Some(second) = &mut self.second {
+                    second.len = 0;
+                }
+            }
+        }
+    }
+
+    /// builds a range out of raw segments, for buffers delivered by a mechanism the crate doesn't
+    /// model (e.g. a custom opcode). `first_bid` reports u16::MAX for such a range.
+    ///
+    /// # Safety
+    /// - both segments must lie in the pool of the ring the range is later recycled to, each one
+    ///   starting on a buffer boundary, `second` at buffer 0 (the wrap at the end of the ring)
+    /// - `len` bytes must be readable from each pointer and the buffers must be held by the app,
+    ///   not the kernel, until the range is recycled
+    pub unsafe fn from_segments(
+        first: (NonNull<u8>, usize),
+        second: Option<(NonNull<u8>, usize)>,
+    ) -> Self {
+        Self {
+            first_bid: u16::MAX,
+            first: BufferRangeInner::new(first.0, first.1),
+            second: second.map(|(ptr, len)| BufferRangeInner::new(ptr, len)),
+            _not_send_sync: PhantomData,
+        }
+    }
+
+    /// bid of the buffer the range starts at, the one reported by the completion
+    pub fn first_bid(&self) -> u16 {
+        self.first_bid
+    }
+
+    /// true if the range crosses the end of the ring, i.e. as_parts has a second segment
+    pub fn is_wrapped(&self) -> bool {
+        self.second.as_ref().is_some_and(|s| s.len > 0)
+    }
+
+    /// the whole range as one slice, None if it wrapped and you need as_parts
+    pub fn as_contiguous(&self) -> Option<&[u8]> {
+        (!self.is_wrapped()).then(|| self.first.as_slice())
+    }
+
+    /// number of contiguous segments, 1 or 2
+    pub fn segment_count(&self) -> usize {
+        1 + self.is_wrapped() as usize
+    }
+
+    /// total number of bytes in the range
+    pub fn len(&self) -> usize {
+        self.first.len + self.second.as_ref().map_or(0, |s| s.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// folds over the bytes of both segments, a slice at a time
+    pub fn fold_bytes<B, F: FnMut(B, &[u8]) -> B>(&self, init: B, mut f: F) -> B {
+        let (first, second) = self.as_parts();
+        let acc = f(init, first);
+        match second {
+            Some(second) => f(acc, second),
+            None => acc,
+        }
+    }
+
+    /// adler-32 of the range, for integrity checks without copying it out
+    #[cfg(feature = "checksum")]
+    pub fn adler32(&self) -> u32 {
+        const MOD_ADLER: u32 = 65521;
+        let (a, b) = self.fold_bytes((1u32, 0u32), |(mut a, mut b), bytes| {
+            for &byte in bytes {
+                a = (a + byte as u32) % MOD_ADLER;
+                b = (b + a) % MOD_ADLER;
+            }
+            (a, b)
+        });
+        (b << 16) | a
+    }
+
+    /// writes both segments to `w` with vectored writes, looping on short writes
+    pub fn write_all_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let (first, second) = self.as_parts();
+        let mut parts = [
+            IoSlice::new(first),
+            IoSlice::new(second.unwrap_or_default()),
+        ];
+        let mut bufs = &mut parts[..];
+        // drops the empty slices up front
+        IoSlice::advance_slices(&mut bufs, 0);
+        while !bufs.is_empty() {
+            match w.write_vectored(bufs) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// writes the whole range at `offset` in the file `fd` with pwritev, both segments in one
+    /// call, looping on short writes. the file position of `fd` is left untouched.
+    /// returns the bytes written, the length of the range.
+    pub fn pwrite_to<Fd: AsFd>(&self, fd: Fd, offset: u64) -> io::Result<usize> {
+        let (first, second) = self.as_parts();
+        let mut parts = [
+            IoSlice::new(first),
+            IoSlice::new(second.unwrap_or_default()),
+        ];
+        let mut bufs = &mut parts[..];
+        IoSlice::advance_slices(&mut bufs, 0);
+        let mut written = 0;
+        while !bufs.is_empty() {
+            match rustix::io::pwritev(&fd, bufs, offset + written as u64) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    IoSlice::advance_slices(&mut bufs, n);
+                    written += n;
+                }
+                Err(rustix::io::Errno::INTR) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(written)
+    }
+
+    /// the bytes of `range` across both segments, borrowed from the pool unless the range
+    /// straddles the wrap, in which case they are copied. None if the range is past the end
+    pub fn slice(&self, range: Range<usize>) -> Option<Cow<'_, [u8]>> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        let (first, second) = self.as_parts();
+        let second = second.unwrap_or_default();
+        let split = first.len();
+        Some(match (range.start, range.end) {
+            (_, end) if end <= split => Cow::Borrowed(&first[range]),
+            (start, end) if start >= split => Cow::Borrowed(&second[start - split..end - split]),
+            (start, end) => Cow::Owned([&first[start..], &second[..end - split]].concat()),
+        })
+    }
+
+    /// frames separated by `delim` (excluded), like slice::split: the piece after the last
+    /// delimiter comes last, possibly empty. frames are borrowed from the pool, except the one
+    /// straddling the wrap which is copied when it has bytes on both sides.
+    pub fn split_on(&self, delim: u8) -> impl Iterator<Item = Cow<'_, [u8]>> + '_ {
+        let (first, second) = self.as_parts();
+        let (head, joined, rest) = match second {
+            None => (Some(first), None, None),
+            Some(second) => {
+                let (head, left) = match first.iter().rposition(|&b| b == delim) {
+                    Some(p) => (Some(&first[..p]), &first[p + 1..]),
+                    None => (None, first),
+                };
+                let (right, rest) = match second.iter().position(|&b| b == delim) {
+                    Some(q) => (&second[..q], Some(&second[q + 1..])),
+                    None => (second, None),
+                };
+                let joined = match (left.is_empty(), right.is_empty()) {
+                    (true, _) => Cow::Borrowed(right),
+                    (_, true) => Cow::Borrowed(left),
+                    _ => Cow::Owned([left, right].concat()),
+                };
+                (head, Some(joined), rest)
+            }
+        };
+        head.into_iter()
+            .flat_map(move |segment| frames(segment, delim))
+            .chain(joined)
+            .chain(
+                rest.into_iter()
+                    .flat_map(move |segment| frames(segment, delim)),
+            )
+    }
+
+    /// writes one iovec per segment at the start of `iov`, for the msg_iov of a raw msghdr,
+    /// and returns how many were written. panics if `iov` is shorter than segment_count().
+    pub fn fill_iovecs(&self, iov: &mut [libc::iovec]) -> usize {
+        let count = self.segment_count();
+        assert!(
+            iov.len() >= count,
+            "{count} iovecs needed, room for {}",
+            iov.len()
+        );
+        for (slot, segment) in iov.iter_mut().zip(self) {
+            *slot = libc::iovec {
+                iov_base: segment.as_ptr() as *mut _,
+                iov_len: segment.len(),
+            };
+        }
+        count
+    }
+
+    /// hexdump of both segments as one contiguous run, capped for big ranges
+    pub fn hexdump(&self) -> HexDump<'_> {
+        let (first, second) = self.as_parts();
+        HexDump::new(first, second)
+    }
+}
+
+/// compares segment by segment, without gathering the range in one buffer
+impl<const SIZE: u32> PartialEq<[u8]> for BufferRange<SIZE> {
+    fn eq(&self, other: &[u8]) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let (first, second) = self.as_parts();
+        let (head, tail) = other.split_at(first.len());
+        first == head && second.unwrap_or_default() == tail
+    }
+}
+
+impl<const SIZE: u32> PartialEq<&[u8]> for BufferRange<SIZE> {
+    fn eq(&self, other: &&[u8]) -> bool {
+        *self == **other
+    }
+}
+
+fn frames(segment: &[u8], delim: u8) -> impl Iterator<Item = Cow<'_, [u8]>> {
+    segment.split(move |&b| b == delim).map(Cow::Borrowed)
+}
+
+/// byte at a logical offset of the range, panics past the end like slice indexing
+impl<const SIZE: u32> Index<usize> for BufferRange<SIZE> {
+    type Output = u8;
+
+    fn index(&self, index: usize) -> &u8 {
+        let (first, second) = self.as_parts();
+        match index.checked_sub(first.len()) {
+            None => &first[index],
+            Some(offset) => &second.unwrap_or_default()[offset],
+        }
+    }
+}
+
+/// copies both segments out, the range still has to be recycled
+impl<const SIZE: u32> From<&BufferRange<SIZE>> for Vec<u8> {
+    fn from(range: &BufferRange<SIZE>) -> Self {
+        let (first, second) = range.as_parts();
+        let mut out = Vec::with_capacity(range.len());
+        out.extend_from_slice(first);
+        out.extend_from_slice(second.unwrap_or_default());
+        out
+    }
+}
+
+/// iterates over the one or two contiguous segments of the range
+impl<'a, const SIZE: u32> IntoIterator for &'a BufferRange<SIZE> {
+    type Item = &'a [u8];
+    type IntoIter = std::iter::Chain<std::iter::Once<&'a [u8]>, std::option::IntoIter<&'a [u8]>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let (first, second) = self.as_parts();
+        std::iter::once(first).chain(second)
+    }
+}
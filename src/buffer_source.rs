@@ -0,0 +1,44 @@
+use crate::{RingBuffer, buffer::Buffer, buffer_range::BufferRange};
+
+/// the get/recycle surface of a RingBuffer, so consumers can swap in a MockRing in their tests
+pub trait BufferSource<const BUFFER_SIZE: u32> {
+    fn get_buffer(&self, bid: u16, len: usize) -> Option<Buffer<BUFFER_SIZE>>;
+    fn get_buffers_range(&self, bid: u16, len: usize) -> Option<BufferRange<BUFFER_SIZE>>;
+    fn recycle_buffer(&self, buffer: &Buffer<BUFFER_SIZE>);
+    fn recycle_buffers_range(&self, range: &BufferRange<BUFFER_SIZE>);
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> BufferSource<BUFFER_SIZE>
+    for RingBuffer<BUFFER_SIZE, RING_SIZE>
+{
+    fn get_buffer(&self, bid: u16, len: usize) -> Option<Buffer<BUFFER_SIZE>> {
+        RingBuffer::get_buffer(self, bid, len)
+    }
+
+    fn get_buffers_range(&self, bid: u16, len: usize) -> Option<BufferRange<BUFFER_SIZE>> {
+        RingBuffer::get_buffers_range(self, bid, len)
+    }
+
+    fn recycle_buffer(&self, buffer: &Buffer<BUFFER_SIZE>) {
+        RingBuffer::recycle_buffer(self, buffer)
+    }
+
+    fn recycle_buffers_range(&self, range: &BufferRange<BUFFER_SIZE>) {
+        RingBuffer::recycle_buffers_range(self, range)
+    }
+}
+
+/// a buffer group registered on a ring, what a runtime's recv path needs: the group id to put
+/// in its SQEs, then the BufferSource surface to turn completions into ranges and recycle them.
+/// lets thread-per-core runtime adapters be generic over the buffer provider.
+pub trait ProvidedBuffers<const BUFFER_SIZE: u32>: BufferSource<BUFFER_SIZE> {
+    fn group_id(&self) -> u16;
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> ProvidedBuffers<BUFFER_SIZE>
+    for RingBuffer<BUFFER_SIZE, RING_SIZE>
+{
+    fn group_id(&self) -> u16 {
+        RingBuffer::group_id(self)
+    }
+}
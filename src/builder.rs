@@ -0,0 +1,232 @@
+use std::{os::fd::BorrowedFd, ptr::NonNull, rc::Rc};
+
+use io_uring::{IoUring, Submitter};
+
+use rustix::mm::{MapFlags, ProtFlags};
+
+use crate::{Advice, RingBuffer, flags::BufRingFlags, recorder::BufRingRecorder};
+
+/// how the ring entries and the pool get mapped, the default is MAP_PRIVATE | MAP_POPULATE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapOptions {
+    /// prefault the pages at map time (MAP_POPULATE), skip it to avoid upfront RSS
+    pub populate: bool,
+    /// MAP_SHARED instead of MAP_PRIVATE, building fails with InvalidInput along with
+    /// single_mapping or mlock
+    pub shared: bool,
+    /// back the pool with huge pages (MAP_HUGETLB), the pool size should be a multiple of the
+    /// huge page size. the ring entries always use regular pages unless mapped with the pool.
+    pub hugetlb: bool,
+    /// protection of the pool pages. the kernel copies received data through them,
+    /// so a pool provided for recv must keep WRITE, see `RingBuffer::protect_pool` to drop it later.
+    pub pool_prot: ProtFlags,
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        Self {
+            populate: true,
+            shared: false,
+            hugetlb: false,
+            pool_prot: ProtFlags::READ | ProtFlags::WRITE,
+        }
+    }
+}
+
+impl MapOptions {
+    pub(crate) fn validate(&self) -> std::io::Result<()> {
+        if self.hugetlb && cfg!(feature = "guard-pages") {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "guard pages can't be mprotected inside a MAP_HUGETLB pool",
+            ));
+        }
+        if !self.pool_prot.contains(ProtFlags::WRITE) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "recv needs a writable pool, the kernel would fail with EFAULT",
+            ));
+        }
+        Ok(())
+    }
+
+    /// flags for a mapping, `huge` is false for the ring entries mapped on their own
+    pub(crate) fn map_flags(&self, huge: bool) -> MapFlags {
+        let mut flags = match self.shared {
+            true => MapFlags::SHARED,
+            false => MapFlags::PRIVATE,
+        };
+        if self.populate {
+            flags |= MapFlags::POPULATE;
+        }
+        if huge && self.hugetlb {
+            flags |= MapFlags::HUGETLB;
+        }
+        flags
+    }
+}
+
+/// configures a RingBuffer before it gets registered, `RingBuffer::new` covers the default case.
+pub struct RingBufferBuilder<const BUFFER_SIZE: u32, const RING_SIZE: u16> {
+    pub(crate) group_id: u16,
+    pub(crate) flags: u16,
+    pub(crate) entry_len: u32,
+    pub(crate) mlock: bool,
+    pub(crate) single_mapping: bool,
+    pub(crate) advice: Vec<Advice>,
+    pub(crate) fill_pattern: Option<u8>,
+    /// buffers published at registration, the rest is left to `RingBuffer::provide`
+    pub(crate) initial: u16,
+    pub(crate) map_options: MapOptions,
+    pub(crate) low_water_mark: u16,
+    pub(crate) max_outstanding_bytes: usize,
+    pub(crate) recorder: Option<Rc<dyn BufRingRecorder>>,
+    /// pool of another ring registered over instead of mapping one, see `share_pool_of`
+    pub(crate) shared_pool: Option<NonNull<u8>>,
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBufferBuilder<BUFFER_SIZE, RING_SIZE> {
+    pub fn new(buffer_group_id: u16) -> Self {
+        Self {
+            group_id: buffer_group_id,
+            flags: 0,
+            entry_len: BUFFER_SIZE,
+            mlock: false,
+            single_mapping: false,
+            advice: Vec::new(),
+            fill_pattern: None,
+            initial: RING_SIZE,
+            map_options: MapOptions::default(),
+            low_water_mark: 0,
+            max_outstanding_bytes: usize::MAX,
+            recorder: None,
+            shared_pool: None,
+        }
+    }
+
+    /// flags passed to register_buf_ring_with_flags
+    pub fn flags(mut self, flags: u16) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// same as flags, typed
+    pub fn ring_flags(self, flags: BufRingFlags) -> Self {
+        self.flags(flags.bits())
+    }
+
+    /// length advertised to the kernel for every entry, clamped to BUFFER_SIZE
+    pub fn entry_len(mut self, entry_len: u32) -> Self {
+        self.entry_len = entry_len;
+        self
+    }
+
+    /// mlock the pool and the ring entries so they never get swapped out.
+    /// building fails if the lock exceeds RLIMIT_MEMLOCK.
+    pub fn mlock(mut self, mlock: bool) -> Self {
+        self.mlock = mlock;
+        self
+    }
+
+    /// carve the ring entries and the pool out of one mapping (one mmap, one VMA) instead of two.
+    /// the pool starts on the first page boundary after the ring entries, without guard pages.
+    pub fn single_mapping(mut self, single_mapping: bool) -> Self {
+        self.single_mapping = single_mapping;
+        self
+    }
+
+    /// madvise hint applied over the pool once mapped, can be called several times
+    pub fn advise(mut self, advice: Advice) -> Self {
+        self.advice.push(advice);
+        self
+    }
+
+    /// memsets the whole pool to `byte` (e.g. 0xDD) before registration, so reading past what
+    /// the kernel wrote shows up in the data. the pool is left zeroed by default.
+    pub fn fill_pattern(mut self, byte: u8) -> Self {
+        self.fill_pattern = Some(byte);
+        self
+    }
+
+    /// registers the group over `owner`'s pool instead of mapping a new one, so buffers can move
+    /// between the two groups with `RingBuffer::recycle_range_into`. nothing is published at
+    /// registration, a buffer reaches this group only once recycled into it. building fails
+    /// combined with single_mapping, advise, fill_pattern or a non-zero initial.
+    /// # Safety
+    /// the built RingBuffer must be dropped before `owner`. its provide, acquire_send_buffer,
+    /// fill_buffer, reset and grow fail with InvalidInput: they would hand out buffers `owner`
+    /// may have given to the kernel too.
+    pub unsafe fn share_pool_of(mut self, owner: &RingBuffer<BUFFER_SIZE, RING_SIZE>) -> Self {
+        self.shared_pool = Some(unsafe { owner.pool_ptr() });
+        self.initial = 0;
+        self
+    }
+
+    /// register the group with no buffer published, see `RingBuffer::provide`
+    pub fn empty(self, empty: bool) -> Self {
+        self.initial(if empty { 0 } else { RING_SIZE })
+    }
+
+    /// publish only the first `initial` buffers at registration, the others wait for
+    /// `RingBuffer::provide`. building fails if `initial` exceeds RING_SIZE.
+    pub fn initial(mut self, initial: u16) -> Self {
+        self.initial = initial;
+        self
+    }
+
+    /// prefault the pool and the ring at map time (MAP_POPULATE, the default). false maps them
+    /// lazily, a multi-gigabyte pool then builds without waiting on every page and faults on
+    /// first touch, see `RingBuffer::prefault_buffers` to warm part of it.
+    pub fn populate(mut self, populate: bool) -> Self {
+        self.map_options.populate = populate;
+        self
+    }
+
+    pub fn map_options(mut self, map_options: MapOptions) -> Self {
+        self.map_options = map_options;
+        self
+    }
+
+    /// `RingBuffer::is_starving` turns true when fill_estimate drops to this many buffers
+    pub fn low_water_mark(mut self, low_water_mark: u16) -> Self {
+        self.low_water_mark = low_water_mark;
+        self
+    }
+
+    /// cap on the bytes of buffers held by the app (whole buffers) enforced by
+    /// `RingBuffer::try_get_buffers_range`, unlimited by default
+    pub fn max_outstanding_bytes(mut self, max_outstanding_bytes: usize) -> Self {
+        self.max_outstanding_bytes = max_outstanding_bytes;
+        self
+    }
+
+    /// reports the ring counters to `recorder`, none by default
+    pub fn recorder(self, recorder: impl BufRingRecorder + 'static) -> Self {
+        self.recorder_rc(Some(Rc::new(recorder)))
+    }
+
+    pub(crate) fn recorder_rc(mut self, recorder: Option<Rc<dyn BufRingRecorder>>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    pub fn build(self, ring: &IoUring) -> std::io::Result<RingBuffer<BUFFER_SIZE, RING_SIZE>> {
+        RingBuffer::from_builder(ring, &self)
+    }
+
+    /// same as build, registering through `submitter`, see `RingBuffer::new_with_submitter`
+    pub fn build_with_submitter(
+        self,
+        submitter: &Submitter<'_>,
+    ) -> std::io::Result<RingBuffer<BUFFER_SIZE, RING_SIZE>> {
+        RingBuffer::from_builder_submitter(submitter, &self)
+    }
+
+    /// same as build, registering through the raw ring fd, see `RingBuffer::new_with_fd`
+    pub fn build_with_fd(
+        self,
+        ring_fd: BorrowedFd<'_>,
+    ) -> std::io::Result<RingBuffer<BUFFER_SIZE, RING_SIZE>> {
+        RingBuffer::from_builder_fd(ring_fd, &self)
+    }
+}
@@ -0,0 +1,45 @@
+use io_uring::IoUring;
+
+use crate::RingBuffer;
+
+/// plain data describing a registered group, enough to register an equivalent one later,
+/// e.g. in the process taking over after a planned restart. see `RingBuffer::descriptor`
+/// and `RingBuffer::from_descriptor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RingDescriptor {
+    pub group_id: u16,
+    pub ring_size: u16,
+    pub buffer_size: u32,
+    pub flags: u16,
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    pub fn descriptor(&self) -> RingDescriptor {
+        RingDescriptor {
+            group_id: self.id,
+            ring_size: RING_SIZE,
+            buffer_size: BUFFER_SIZE,
+            flags: self.flags.get(),
+        }
+    }
+
+    /// registers a group equivalent to the one `descriptor` was taken from. only the shape is
+    /// restored (group id, sizes, flags): the ring is mapped on a fresh zeroed pool with every
+    /// buffer provided, neither the data of the old pool nor the buffers it had checked out are
+    /// carried over. fails with InvalidInput if the sizes of the descriptor don't match
+    /// BUFFER_SIZE and RING_SIZE.
+    pub fn from_descriptor(ring: &IoUring, descriptor: &RingDescriptor) -> std::io::Result<Self> {
+        if (descriptor.buffer_size, descriptor.ring_size) != (BUFFER_SIZE, RING_SIZE) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "descriptor of {} buffers of {} bytes, not {RING_SIZE} of {BUFFER_SIZE}",
+                    descriptor.ring_size, descriptor.buffer_size
+                ),
+            ));
+        }
+        Self::builder(descriptor.group_id)
+            .flags(descriptor.flags)
+            .build(ring)
+    }
+}
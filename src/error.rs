@@ -0,0 +1,98 @@
+use std::{error::Error, fmt, io};
+
+/// typed cause carried inside the io::Error returned by the constructors,
+/// get it back with `err.get_ref().and_then(|e| e.downcast_ref::<RingBufferError>())`.
+#[derive(Debug)]
+pub enum RingBufferError {
+    /// mapping the buffer pool failed
+    PoolMmap { bytes: usize, source: io::Error },
+    /// mapping the ring entries failed
+    RingMmap { bytes: usize, source: io::Error },
+    /// mlock failed, RLIMIT_MEMLOCK is probably too low
+    Mlock { bytes: usize, source: io::Error },
+    /// the kernel refused a ring of `entries` entries, it accepts up to `max`
+    RingTooLarge { entries: u16, max: u16 },
+    /// the ring entries at `addr` don't start on a page boundary, the kernel would refuse them
+    RingMisaligned { addr: usize, page_size: usize },
+    /// another buffer ring is already registered with this group id on the IoUring
+    GroupIdInUse(u16),
+    /// taking a range would pin `outstanding` bytes of buffers, over the configured `max`
+    Backpressure { outstanding: usize, max: usize },
+    /// a completion reported a buffer the kernel doesn't own: out of the ring, never provided,
+    /// or still held by the app (e.g. a double completion)
+    UnexpectedBid(u16),
+}
+
+impl RingBufferError {
+    fn source_error(&self) -> Option<&io::Error> {
+        match self {
+            Self::PoolMmap { source, .. }
+            | Self::RingMmap { source, .. }
+            | Self::Mlock { source, .. } => Some(source),
+            Self::RingTooLarge { .. }
+            | Self::RingMisaligned { .. }
+            | Self::GroupIdInUse(_)
+            | Self::Backpressure { .. }
+            | Self::UnexpectedBid(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for RingBufferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PoolMmap { bytes, source } => {
+                write!(f, "mmap of the {bytes} bytes buffer pool failed: {source}")
+            }
+            Self::RingMmap { bytes, source } => {
+                write!(f, "mmap of the {bytes} bytes ring entries failed: {source}")
+            }
+            Self::Mlock { bytes, source } => write!(
+                f,
+                "mlock of {bytes} bytes failed ({source}), RLIMIT_MEMLOCK is probably too low"
+            ),
+            Self::RingTooLarge { entries, max } => write!(
+                f,
+                "a ring of {entries} entries exceeds the kernel maximum of {max}"
+            ),
+            Self::RingMisaligned { addr, page_size } => write!(
+                f,
+                "ring entries at {addr:#x} are not aligned on the {page_size} bytes page size"
+            ),
+            Self::GroupIdInUse(group_id) => write!(
+                f,
+                "buffer group id {group_id} is already registered on this ring"
+            ),
+            Self::Backpressure { outstanding, max } => write!(
+                f,
+                "{outstanding} bytes of buffers would be held, more than the {max} allowed"
+            ),
+            Self::UnexpectedBid(bid) => {
+                write!(
+                    f,
+                    "completion reported buffer {bid}, not owned by the kernel"
+                )
+            }
+        }
+    }
+}
+
+impl Error for RingBufferError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source_error().map(|e| e as _)
+    }
+}
+
+impl From<RingBufferError> for io::Error {
+    fn from(e: RingBufferError) -> Self {
+        let kind = match &e {
+            RingBufferError::Backpressure { .. } => io::ErrorKind::WouldBlock,
+            RingBufferError::GroupIdInUse(_) => io::ErrorKind::AlreadyExists,
+            RingBufferError::UnexpectedBid(_) => io::ErrorKind::InvalidData,
+            _ => e
+                .source_error()
+                .map_or(io::ErrorKind::InvalidInput, io::Error::kind),
+        };
+        io::Error::new(kind, e)
+    }
+}
@@ -0,0 +1,22 @@
+use io_uring::IoUring;
+
+use crate::RingBuffer;
+
+/// registers buffer rings as an operation on the IoUring
+pub trait IoUringBufRingExt {
+    fn provide_buf_ring<const BUFFER_SIZE: u32, const RING_SIZE: u16>(
+        &self,
+        buffer_group_id: u16,
+        flags: u16,
+    ) -> std::io::Result<RingBuffer<BUFFER_SIZE, RING_SIZE>>;
+}
+
+impl IoUringBufRingExt for IoUring {
+    fn provide_buf_ring<const BUFFER_SIZE: u32, const RING_SIZE: u16>(
+        &self,
+        buffer_group_id: u16,
+        flags: u16,
+    ) -> std::io::Result<RingBuffer<BUFFER_SIZE, RING_SIZE>> {
+        RingBuffer::new(self, flags, buffer_group_id)
+    }
+}
@@ -0,0 +1,13 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// flags of io_uring_buf_reg, passed when the group gets registered.
+    /// bits the wrapper doesn't name yet can still go through `from_bits_retain`
+    /// or the raw u16 constructors.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct BufRingFlags: u16 {
+        /// IOU_PBUF_RING_INC: the kernel consumes buffers incrementally, a completion may
+        /// use only part of a buffer and the next one continues in the same buffer (6.12+)
+        const INC = 1 << 1;
+    }
+}
@@ -0,0 +1,79 @@
+use io_uring::{cqueue, opcode, squeue, types::Fd};
+
+use crate::{RingBuffer, buffer_range::BufferRange};
+
+/// msghdr and iovecs of a forward, boxed so their addresses stay put while the SQE is in flight
+struct ForwardMsg {
+    iovecs: [libc::iovec; 2],
+    msghdr: libc::msghdr,
+}
+
+/// a range being sent with SendMsgZc straight from the pool, see `RingBuffer::forward_zc`.
+/// the range is recycled once the kernel is done reading it. dropping a pending forward before
+/// that leaks its buffers rather than recycling memory the kernel may still read.
+pub struct PendingForward<'a, const BUFFER_SIZE: u32, const RING_SIZE: u16> {
+    ring_buffer: &'a RingBuffer<BUFFER_SIZE, RING_SIZE>,
+    range: Option<BufferRange<BUFFER_SIZE>>,
+    msg: Box<ForwardMsg>,
+    fd: Fd,
+    result: Option<i32>,
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    /// prepares a zero-copy send of `range` to `fd`, with the segments of the range as iovecs.
+    /// push `entry()`, then feed the CQEs of that SQE to `complete` until it returns Some.
+    pub fn forward_zc(
+        &self,
+        fd: Fd,
+        range: BufferRange<BUFFER_SIZE>,
+    ) -> PendingForward<'_, BUFFER_SIZE, RING_SIZE> {
+        let empty = libc::iovec {
+            iov_base: std::ptr::null_mut(),
+            iov_len: 0,
+        };
+        let mut msg = Box::new(ForwardMsg {
+            iovecs: [empty; 2],
+            msghdr: unsafe { std::mem::zeroed() },
+        });
+        msg.msghdr.msg_iovlen = range.fill_iovecs(&mut msg.iovecs) as _;
+        msg.msghdr.msg_iov = msg.iovecs.as_mut_ptr();
+        PendingForward {
+            ring_buffer: self,
+            range: Some(range),
+            msg,
+            fd,
+            result: None,
+        }
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> PendingForward<'_, BUFFER_SIZE, RING_SIZE> {
+    /// the SendMsgZc SQE, set its user_data to route the CQEs back here.
+    /// the pending forward must stay alive until `complete` returned Some.
+    pub fn entry(&self) -> squeue::Entry {
+        opcode::SendMsgZc::new(self.fd, &self.msg.msghdr).build()
+    }
+
+    /// feeds a CQE of the send. the first one carries the result, the notification CQE that
+    /// follows means the kernel released the pages: the range is recycled and the result of
+    /// the send returned. a failed send gets no notification and completes right away.
+    pub fn complete(&mut self, cqe: &cqueue::Entry) -> Option<std::io::Result<usize>> {
+        if !cqueue::notif(cqe.flags()) {
+            self.result = Some(cqe.result());
+            if cqueue::more(cqe.flags()) {
+                return None;
+            }
+        }
+        let range = self.range.take()?;
+        self.ring_buffer.recycle_buffers_range(&range);
+        Some(match self.result? {
+            res if res < 0 => Err(std::io::Error::from_raw_os_error(-res)),
+            res => Ok(res as usize),
+        })
+    }
+
+    /// the range being sent, None once recycled
+    pub fn range(&self) -> Option<&BufferRange<BUFFER_SIZE>> {
+        self.range.as_ref()
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    os::fd::{AsRawFd, RawFd},
+};
+
+use io_uring::IoUring;
+
+thread_local! {
+    static USED: RefCell<HashMap<RawFd, BTreeSet<u16>>> = RefCell::new(HashMap::new());
+}
+
+/// keeps track of the buffer group ids in use on each IoUring of this thread.
+/// every RingBuffer claims its id once registered and releases it on drop.
+pub struct GroupIdAllocator;
+
+impl GroupIdAllocator {
+    /// lowest group id not used by a RingBuffer on this ring
+    pub fn next_free(ring: &IoUring) -> Option<u16> {
+        Self::free_ids(ring).next()
+    }
+
+    /// the group ids not used by a RingBuffer on this ring, lowest first. a group registered
+    /// behind the allocator's back (a bare Submitter, another library) can still hold one of them,
+    /// callers move on to the next when the kernel answers EEXIST.
+    pub(crate) fn free_ids(ring: &IoUring) -> impl Iterator<Item = u16> + use<> {
+        let used = USED.with_borrow(|used| used.get(&ring.as_raw_fd()).cloned());
+        (0..=u16::MAX).filter(move |id| used.as_ref().is_none_or(|ids| !ids.contains(id)))
+    }
+
+    pub fn is_used(ring: &IoUring, buffer_group_id: u16) -> bool {
+        USED.with_borrow(|used| {
+            used.get(&ring.as_raw_fd())
+                .is_some_and(|ids| ids.contains(&buffer_group_id))
+        })
+    }
+
+    pub(crate) fn claim(ring_fd: RawFd, buffer_group_id: u16) {
+        USED.with_borrow_mut(|used| {
+            used.entry(ring_fd).or_default().insert(buffer_group_id);
+        });
+    }
+
+    pub(crate) fn release(ring_fd: RawFd, buffer_group_id: u16) {
+        USED.with_borrow_mut(|used| {
+            if let Some(ids) = used.get_mut(&ring_fd) {
+                ids.remove(&buffer_group_id);
+                if ids.is_empty() {
+                    used.remove(&ring_fd);
+                }
+            }
+        });
+    }
+}
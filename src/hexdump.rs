@@ -0,0 +1,59 @@
+use std::fmt::{self, Display, Formatter};
+
+/// bytes dumped past this are replaced by an ellipsis
+const MAX_DUMP_BYTES: usize = 4096;
+const BYTES_PER_LINE: usize = 16;
+
+/// hexdump of one or two segments as if they were contiguous,
+/// one line per 16 bytes: offset, hex bytes and ascii.
+pub struct HexDump<'a> {
+    first: &'a [u8],
+    second: &'a [u8],
+}
+
+impl<'a> HexDump<'a> {
+    pub(crate) fn new(first: &'a [u8], second: Option<&'a [u8]>) -> Self {
+        Self {
+            first,
+            second: second.unwrap_or_default(),
+        }
+    }
+}
+
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let total = self.first.len() + self.second.len();
+        let shown = total.min(MAX_DUMP_BYTES);
+        let mut bytes = self.first.iter().chain(self.second).copied().take(shown);
+        let mut line = [0u8; BYTES_PER_LINE];
+        let mut offset = 0;
+        while offset < shown {
+            let n = (shown - offset).min(BYTES_PER_LINE);
+            for b in &mut line[..n] {
+                *b = bytes.next().unwrap_or_default();
+            }
+            write!(f, "{offset:08x} ")?;
+            for i in 0..BYTES_PER_LINE {
+                match line[..n].get(i) {
+                    Some(b) => write!(f, " {b:02x}")?,
+                    None => f.write_str("   ")?,
+                }
+            }
+            f.write_str("  |")?;
+            for &b in &line[..n] {
+                let c = if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f, "|")?;
+            offset += n;
+        }
+        if total > shown {
+            writeln!(f, "... ({} more bytes)", total - shown)?;
+        }
+        Ok(())
+    }
+}
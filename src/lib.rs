@@ -1,14 +1,127 @@
-use std::{cell::UnsafeCell, marker::PhantomData, sync::atomic::Ordering};
+use std::{
+    cell::{Cell, RefCell, UnsafeCell},
+    marker::PhantomData,
+    ops::Range,
+    os::fd::{AsRawFd, BorrowedFd, RawFd},
+    ptr::NonNull,
+    rc::Rc,
+    sync::atomic::Ordering,
+};
 
+/// forwards to tracing when the `tracing` feature is on, compiles to nothing otherwise
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+mod batch;
+mod bid_set;
 pub mod buffer;
 mod buffer_pool;
+pub mod buffer_range;
+pub mod buffer_source;
+pub mod builder;
+pub mod descriptor;
+pub mod error;
+pub mod ext;
+pub mod flags;
+pub mod forward;
+pub mod group_id;
+pub mod hexdump;
 mod mapped_ring;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod multishot;
+mod read;
+pub mod recorder;
+pub mod recv_result;
+pub mod recvmsg;
+pub mod ring_set;
+#[cfg(feature = "stream")]
+pub mod stream;
+mod sys;
+pub mod tiered;
+#[cfg(feature = "tokio")]
+pub mod tokio_reader;
+#[cfg(feature = "zcrx")]
+pub mod zcrx;
 
-use io_uring::{IoUring, types::BufRingEntry};
+use io_uring::{
+    IoUring, Submitter, opcode, squeue,
+    types::{BufRingEntry, Fd},
+};
+pub use rustix::mm::{Advice, ProtFlags};
 
-use crate::{buffer::Buffer, buffer_pool::BufferPool, mapped_ring::MmapedRing};
+use crate::{
+    bid_set::BidSet,
+    buffer::{Buffer, BufferMut},
+    buffer_pool::BufferPool,
+    buffer_range::{BufferRange, BufferRangeInner},
+    builder::{MapOptions, RingBufferBuilder},
+    error::RingBufferError,
+    flags::BufRingFlags,
+    group_id::GroupIdAllocator,
+    mapped_ring::MmapedRing,
+    recorder::BufRingRecorder,
+};
 
 type BufferId = u16;
+type RecycleHook = Box<dyn FnMut(BufferId)>;
+
+/// largest RING_SIZE: the biggest power of two fitting in a u16, also the kernel limit for a buffer ring
+pub const MAX_RING_SIZE: u16 = 1 << 15;
+
+/// largest buffer ring the running kernel accepts, probed by registering throwaway rings
+/// of decreasing power-of-two sizes on a free group id.
+pub fn max_ring_entries(ring: &IoUring) -> std::io::Result<u16> {
+    let mut group_ids = GroupIdAllocator::free_ids(ring);
+    let mut group_id = group_ids.next().ok_or(std::io::ErrorKind::AddrInUse)?;
+    let options = MapOptions {
+        populate: false,
+        ..Default::default()
+    };
+    let mut entries = MAX_RING_SIZE;
+    while entries > 0 {
+        let probe = MmapedRing::build(entries as _, false, &options)?;
+        let registered = unsafe {
+            ring.submitter().register_buf_ring_with_flags(
+                probe.inner().as_ptr() as _,
+                entries,
+                group_id,
+                0,
+            )
+        };
+        match registered {
+            Ok(()) => {
+                ring.submitter().unregister_buf_ring(group_id)?;
+                return Ok(entries);
+            }
+            Err(e) if e.raw_os_error() == Some(rustix::io::Errno::INVAL.raw_os_error()) => {
+                entries /= 2
+            }
+            // registered behind the allocator's back, probe again on the next free id
+            Err(e) if e.raw_os_error() == Some(rustix::io::Errno::EXIST.raw_os_error()) => {
+                group_id = group_ids.next().ok_or(std::io::ErrorKind::AddrInUse)?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// fails with RingMisaligned unless `ring_ptr` is page aligned, the kernel rejects any other ring
+/// with a bare EINVAL. RingBuffer checks its own ring before registering, call this before
+/// registering ring entries mapped by other means.
+pub fn check_ring_alignment(ring_ptr: *const BufRingEntry) -> Result<(), RingBufferError> {
+    let addr = ring_ptr as usize;
+    let page_size = rustix::param::page_size();
+    if !addr.is_multiple_of(page_size) {
+        return Err(RingBufferError::RingMisaligned { addr, page_size });
+    }
+    Ok(())
+}
 
 /// Helper to get the current tail value from a ring buffer
 unsafe fn get_tail(ring_ptr: *const BufRingEntry) -> u16 {
@@ -18,7 +131,9 @@ unsafe fn get_tail(ring_ptr: *const BufRingEntry) -> u16 {
     }
 }
 
-/// Helper to set the tail value on a ring buffer
+/// Helper to set the tail value on a ring buffer.
+/// the Release store pairs with the kernel's smp_load_acquire of the tail, so the entries written
+/// before are visible to whichever kernel thread reads it, the SQPOLL thread included.
 unsafe fn set_tail(ring_ptr: *const BufRingEntry, new_tail: u16) {
     unsafe {
         let tail_ptr = BufRingEntry::tail(ring_ptr) as *const std::sync::atomic::AtomicU16;
@@ -26,18 +141,30 @@ unsafe fn set_tail(ring_ptr: *const BufRingEntry, new_tail: u16) {
     }
 }
 
+/// Helper to mlock a mapped region, with an explicit error when RLIMIT_MEMLOCK is hit
+unsafe fn mlock_region(ptr: *mut std::ffi::c_void, len: usize) -> std::io::Result<()> {
+    unsafe { rustix::mm::mlock(ptr, len) }.map_err(|e| {
+        RingBufferError::Mlock {
+            bytes: len,
+            source: e.into(),
+        }
+        .into()
+    })
+}
+
 /// Sets up a ring entry at the given tail position
-unsafe fn setup_ring_entry<const BUFFER_SIZE: u32, const RING_SIZE: u16>(
+unsafe fn setup_ring_entry<const RING_SIZE: u16>(
     ring_ptr: *mut BufRingEntry,
     tail: u16,
     addr: u64,
+    len: u32,
     bid: u16,
 ) {
     unsafe {
         let idx = (tail as usize) & ((RING_SIZE - 1) as usize);
         let entry = ring_ptr.add(idx);
         (*entry).set_addr(addr);
-        (*entry).set_len(BUFFER_SIZE);
+        (*entry).set_len(len);
         (*entry).set_bid(bid);
     }
 }
@@ -46,75 +173,1372 @@ pub struct RingBuffer<const BUFFER_SIZE: u32, const RING_SIZE: u16> {
     buffer_pool: UnsafeCell<BufferPool<BUFFER_SIZE, RING_SIZE>>,
     mapped_ring: UnsafeCell<MmapedRing>,
     id: u16,
+    flags: Cell<u16>,
+    /// false between `unregister` and `reregister`
+    registered: Cell<bool>,
+    /// length advertised to the kernel per entry, the stride in the pool stays BUFFER_SIZE
+    entry_len: Cell<u32>,
+    /// buffers handed out by get_buffer and not recycled yet
+    in_flight: Cell<u16>,
+    /// highest in_flight reached since construction or the last reset_high_water_mark
+    peak_in_flight: Cell<u16>,
+    /// bids currently checked out, in_flight counts the ones not in `sending`
+    issued: BidSet,
+    /// issued bids checked out by acquire_send_buffer, kept out of the receive-side accounting
+    sending: BidSet,
+    /// bids staged by defer_recycle until the next flush, RING_SIZE slots allocated up front
+    /// (2 bytes per buffer) so staging never allocates nor overflows
+    staged: Box<[Cell<BufferId>]>,
+    staged_len: Cell<u16>,
+    /// bids below this one have been published to the kernel at least once
+    provided: Cell<u16>,
+    /// registered over another ring's pool (`new_sharing_pool`), `provided` stays 0 then
+    shared_pool: bool,
+    /// on a ring sharing another's pool, the bids moved in by recycle_range_into and not moved
+    /// out again: its receive buffers, where an owning ring has the bids below `provided`
+    received: BidSet,
+    received_count: Cell<u16>,
+    low_water_mark: u16,
+    max_outstanding_bytes: usize,
+    on_recycle: RefCell<Option<RecycleHook>>,
+    recorder: Option<Rc<dyn BufRingRecorder>>,
+    /// None when registered through a bare Submitter, the group id isn't tracked then.
+    /// moves along with the reservation when `reregister` picks another ring
+    ring_fd: Cell<Option<RawFd>>,
 }
 
 impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    /// evaluated at compile time when a RingBuffer is built, so bad sizes fail to compile
+    const SIZES_OK: () = {
+        assert!(BUFFER_SIZE > 0, "BUFFER_SIZE must be nonzero");
+        // 65536 doesn't fit in a u16 and a wrapped 0 would give an empty mask
+        assert!(
+            RING_SIZE > 0,
+            "RING_SIZE must be nonzero, it tops out at 32768"
+        );
+        assert!(
+            RING_SIZE.is_power_of_two(),
+            "RING_SIZE must be a power of two"
+        );
+        assert!(RING_SIZE <= MAX_RING_SIZE);
+    };
+
     pub fn group_id(&self) -> u16 {
         self.id
     }
 
+    /// flags the group is currently registered with, updated by `reregister`
+    pub fn flags(&self) -> u16 {
+        self.flags.get()
+    }
+
+    /// same as flags, typed. e.g. `contains(BufRingFlags::INC)` tells whether a completion may
+    /// consume only part of a buffer
+    pub fn ring_flags(&self) -> BufRingFlags {
+        BufRingFlags::from_bits_retain(self.flags.get())
+    }
+
     pub fn new(ring: &IoUring, flags: u16, buffer_group_id: u16) -> std::io::Result<Self> {
-        assert!(BUFFER_SIZE.is_power_of_two());
-        assert!(RING_SIZE.is_power_of_two());
+        Self::with_entry_len(ring, flags, buffer_group_id, BUFFER_SIZE)
+    }
 
-        let mut mmaped_ring: MmapedRing = MmapedRing::build(RING_SIZE as _)?;
-        let slice = mmaped_ring.as_slice();
+    /// same as `new`, but every entry advertises `entry_len` bytes to the kernel instead of BUFFER_SIZE.
+    /// `entry_len` is clamped to BUFFER_SIZE, buffers keep their BUFFER_SIZE stride in the pool.
+    pub fn with_entry_len(
+        ring: &IoUring,
+        flags: u16,
+        buffer_group_id: u16,
+        entry_len: u32,
+    ) -> std::io::Result<Self> {
+        Self::builder(buffer_group_id)
+            .flags(flags)
+            .entry_len(entry_len)
+            .build(ring)
+    }
 
-        unsafe {
-            ring.submitter().register_buf_ring_with_flags(
-                slice.as_ptr() as _,
+    /// same as `new` with the lowest group id not used by another RingBuffer on this ring,
+    /// skipping the ids the kernel reports in use by a group the allocator doesn't know about
+    pub fn new_auto(ring: &IoUring, flags: u16) -> std::io::Result<Self> {
+        for buffer_group_id in GroupIdAllocator::free_ids(ring) {
+            match Self::new(ring, flags, buffer_group_id) {
+                Err(e)
+                    if matches!(
+                        e.get_ref().and_then(|e| e.downcast_ref()),
+                        Some(RingBufferError::GroupIdInUse(_))
+                    ) => {}
+                result => return result,
+            }
+        }
+        Err(std::io::Error::other("no free buffer group id"))
+    }
+
+    /// registers the group without publishing any buffer, use `provide` to hand them out gradually
+    pub fn new_empty(ring: &IoUring, flags: u16, buffer_group_id: u16) -> std::io::Result<Self> {
+        Self::builder(buffer_group_id)
+            .flags(flags)
+            .empty(true)
+            .build(ring)
+    }
+
+    /// registers the group publishing only the first `initial` buffers, the others wait for
+    /// `provide`. `new` is `initial = RING_SIZE` and `new_empty` is `initial = 0`.
+    pub fn new_with_initial(
+        ring: &IoUring,
+        flags: u16,
+        buffer_group_id: u16,
+        initial: u16,
+    ) -> std::io::Result<Self> {
+        Self::builder(buffer_group_id)
+            .flags(flags)
+            .initial(initial)
+            .build(ring)
+    }
+
+    /// same as `new` for callers holding only the ring fd, registers with a raw io_uring_register.
+    /// unregister, reregister, reset and grow still need the IoUring.
+    pub fn new_with_fd(
+        ring_fd: BorrowedFd<'_>,
+        flags: u16,
+        buffer_group_id: u16,
+    ) -> std::io::Result<Self> {
+        Self::builder(buffer_group_id)
+            .flags(flags)
+            .build_with_fd(ring_fd)
+    }
+
+    /// same as `new`, registering through `submitter`, e.g. one split off a shared IoUring.
+    /// the group id isn't tracked by GroupIdAllocator since the ring fd is unknown.
+    pub fn new_with_submitter(
+        submitter: &Submitter<'_>,
+        flags: u16,
+        buffer_group_id: u16,
+    ) -> std::io::Result<Self> {
+        Self::builder(buffer_group_id)
+            .flags(flags)
+            .build_with_submitter(submitter)
+    }
+
+    /// same as `new` with typed flags
+    pub fn new_with_flags(
+        ring: &IoUring,
+        flags: BufRingFlags,
+        buffer_group_id: u16,
+    ) -> std::io::Result<Self> {
+        Self::new(ring, flags.bits(), buffer_group_id)
+    }
+
+    /// same as `new`, reporting its counters to `recorder`
+    pub fn new_with_recorder(
+        ring: &IoUring,
+        flags: u16,
+        buffer_group_id: u16,
+        recorder: impl BufRingRecorder + 'static,
+    ) -> std::io::Result<Self> {
+        Self::builder(buffer_group_id)
+            .flags(flags)
+            .recorder(recorder)
+            .build(ring)
+    }
+
+    /// registers group `buffer_group_id` over `owner`'s pool with nothing published, the
+    /// target of `recycle_range_into`, e.g. to migrate buffers to a ring on another io_uring.
+    /// # Safety
+    /// see `RingBufferBuilder::share_pool_of`
+    pub unsafe fn new_sharing_pool(
+        ring: &IoUring,
+        flags: u16,
+        buffer_group_id: u16,
+        owner: &Self,
+    ) -> std::io::Result<Self> {
+        unsafe { Self::builder(buffer_group_id).share_pool_of(owner) }
+            .flags(flags)
+            .build(ring)
+    }
+
+    pub fn builder(buffer_group_id: u16) -> RingBufferBuilder<BUFFER_SIZE, RING_SIZE> {
+        RingBufferBuilder::new(buffer_group_id)
+    }
+
+    pub(crate) fn from_builder(
+        ring: &IoUring,
+        builder: &RingBufferBuilder<BUFFER_SIZE, RING_SIZE>,
+    ) -> std::io::Result<Self> {
+        Self::from_builder_with(Some(ring.as_raw_fd()), builder, |ring_addr| {
+            let registered = unsafe {
+                ring.submitter().register_buf_ring_with_flags(
+                    ring_addr,
+                    RING_SIZE as _,
+                    builder.group_id,
+                    builder.flags,
+                )
+            };
+            if let Err(e) = registered {
+                // EINVAL is also what an oversized ring gets, tell it apart from the other causes
+                if e.raw_os_error() == Some(rustix::io::Errno::INVAL.raw_os_error())
+                    && let Ok(max) = max_ring_entries(ring)
+                    && max < RING_SIZE
+                {
+                    return Err(RingBufferError::RingTooLarge {
+                        entries: RING_SIZE,
+                        max,
+                    }
+                    .into());
+                }
+                return Err(e);
+            }
+            Ok(())
+        })
+    }
+
+    pub(crate) fn from_builder_fd(
+        ring_fd: BorrowedFd<'_>,
+        builder: &RingBufferBuilder<BUFFER_SIZE, RING_SIZE>,
+    ) -> std::io::Result<Self> {
+        Self::from_builder_with(Some(ring_fd.as_raw_fd()), builder, |ring_addr| unsafe {
+            sys::register_buf_ring(
+                ring_fd,
+                ring_addr,
+                RING_SIZE,
+                builder.group_id,
+                builder.flags,
+            )
+        })
+    }
+
+    pub(crate) fn from_builder_submitter(
+        submitter: &Submitter<'_>,
+        builder: &RingBufferBuilder<BUFFER_SIZE, RING_SIZE>,
+    ) -> std::io::Result<Self> {
+        Self::from_builder_with(None, builder, |ring_addr| unsafe {
+            submitter.register_buf_ring_with_flags(
+                ring_addr,
                 RING_SIZE as _,
-                buffer_group_id,
-                flags,
-            )?
-        };
+                builder.group_id,
+                builder.flags,
+            )
+        })
+    }
+
+    /// maps the ring and the pool, then hands the ring address to `register`
+    fn from_builder_with(
+        ring_fd: Option<RawFd>,
+        builder: &RingBufferBuilder<BUFFER_SIZE, RING_SIZE>,
+        register: impl FnOnce(u64) -> std::io::Result<()>,
+    ) -> std::io::Result<Self> {
+        let () = Self::SIZES_OK;
+        builder.map_options.validate()?;
+        if builder.map_options.shared && (builder.single_mapping || builder.mlock) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a MAP_SHARED mapping can't be combined with single_mapping or mlock",
+            ));
+        }
+        if builder.initial > RING_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "can't publish {} buffers out of a ring of {RING_SIZE}",
+                    builder.initial
+                ),
+            ));
+        }
+        if builder.shared_pool.is_some()
+            && (builder.single_mapping
+                || !builder.advice.is_empty()
+                || builder.fill_pattern.is_some()
+                || builder.initial != 0)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "a shared pool is mapped, filled and published by the ring owning it",
+            ));
+        }
 
-        let bp = BufferPool::<BUFFER_SIZE, RING_SIZE>::new()?;
+        let (mmaped_ring, bp) = if builder.single_mapping {
+            let mmaped_ring = MmapedRing::build_with_trailer(
+                RING_SIZE as _,
+                BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE,
+                builder.mlock,
+                &builder.map_options,
+            )?;
+            let bp = unsafe { BufferPool::from_raw(mmaped_ring.trailer()) };
+            if builder.map_options.pool_prot != MapOptions::default().pool_prot {
+                bp.protect(builder.map_options.pool_prot)?;
+            }
+            (mmaped_ring, bp)
+        } else if let Some(pool) = builder.shared_pool {
+            (
+                MmapedRing::build(RING_SIZE as _, builder.mlock, &builder.map_options)?,
+                unsafe { BufferPool::from_raw(pool.as_ptr()) },
+            )
+        } else {
+            (
+                MmapedRing::build(RING_SIZE as _, builder.mlock, &builder.map_options)?,
+                BufferPool::<BUFFER_SIZE, RING_SIZE>::new(builder.mlock, &builder.map_options)?,
+            )
+        };
 
-        for (bid, slot) in slice.iter_mut().enumerate() {
-            let entry = slot.write(unsafe { std::mem::zeroed() });
-            entry.set_addr(bp.ptr_for_bid(bid as _) as _);
-            entry.set_bid(bid as u16);
-            entry.set_len(BUFFER_SIZE);
+        for &advice in &builder.advice {
+            bp.advise(advice)?;
+        }
+        if let Some(byte) = builder.fill_pattern {
+            bp.fill(byte);
         }
 
-        unsafe {
-            set_tail(slice.as_ptr() as *const BufRingEntry, RING_SIZE);
+        mmaped_ring.check_alignment()?;
+        // the registration is the last fallible step: an error up to it only unmaps the ring and
+        // the pool on drop, the kernel never saw the group. the tail is registered from a known 0.
+        unsafe { set_tail(mmaped_ring.inner().as_ptr(), 0) };
+        register(mmaped_ring.inner().as_ptr() as _).map_err(|e| {
+            match e.raw_os_error() == Some(rustix::io::Errno::EXIST.raw_os_error()) {
+                true => RingBufferError::GroupIdInUse(builder.group_id).into(),
+                false => e,
+            }
+        })?;
+
+        if let Some(ring_fd) = ring_fd {
+            GroupIdAllocator::claim(ring_fd, builder.group_id);
         }
+        trace_event!(
+            debug,
+            group_id = builder.group_id,
+            buffer_size = BUFFER_SIZE,
+            ring_size = RING_SIZE,
+            flags = builder.flags,
+            "buffer ring registered"
+        );
+
+        let entry_len = builder.entry_len.min(BUFFER_SIZE);
+        let provided = builder.initial;
+        Self::fill_ring(&bp, &mmaped_ring, entry_len, provided);
 
         Ok(RingBuffer {
             buffer_pool: UnsafeCell::new(bp),
             mapped_ring: UnsafeCell::new(mmaped_ring),
-            id: buffer_group_id,
+            id: builder.group_id,
+            flags: Cell::new(builder.flags),
+            registered: Cell::new(true),
+            entry_len: Cell::new(entry_len),
+            in_flight: Cell::new(0),
+            peak_in_flight: Cell::new(0),
+            issued: BidSet::new(RING_SIZE),
+            sending: BidSet::new(RING_SIZE),
+            staged: (0..RING_SIZE).map(Cell::new).collect(),
+            staged_len: Cell::new(0),
+            provided: Cell::new(provided),
+            shared_pool: builder.shared_pool.is_some(),
+            received: BidSet::new(RING_SIZE),
+            received_count: Cell::new(0),
+            low_water_mark: builder.low_water_mark,
+            max_outstanding_bytes: builder.max_outstanding_bytes,
+            on_recycle: RefCell::new(None),
+            recorder: builder.recorder.clone(),
+            ring_fd: Cell::new(ring_fd),
         })
     }
 
+    /// writes the first `count` buffers of the pool in the ring and publishes them.
+    /// entries are written field by field: the tail overlays the reserved field of entry 0 and
+    /// the group may already be registered, with an SQPOLL thread reading the tail at any time.
+    fn fill_ring(
+        bp: &BufferPool<BUFFER_SIZE, RING_SIZE>,
+        mmaped_ring: &MmapedRing,
+        entry_len: u32,
+        count: u16,
+    ) {
+        let ring_ptr = mmaped_ring.inner().as_ptr();
+        for bid in 0..count {
+            unsafe {
+                setup_ring_entry::<RING_SIZE>(
+                    ring_ptr,
+                    bid,
+                    bp.ptr_for_bid(bid) as _,
+                    entry_len,
+                    bid,
+                );
+            }
+        }
+
+        unsafe {
+            set_tail(ring_ptr, count);
+        }
+    }
+
+    /// re-arms the whole ring from a clean state, without re-mapping anything.
+    /// the group is unregistered then registered again so the kernel head goes back to 0.
+    /// no recv must be in flight on this group, and every buffer you still hold is invalidated.
+    /// fails with ResourceBusy while a send buffer is checked out, release it first.
+    pub fn reset(&self, ring: &IoUring) -> std::io::Result<()> {
+        self.reject_shared_pool("reset")?;
+        if !self.sending.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ResourceBusy,
+                "send buffers are still checked out",
+            ));
+        }
+        self.unregister(ring)?;
+
+        let bp = unsafe { &*self.buffer_pool.get() };
+        let mmaped_ring = unsafe { &*self.mapped_ring.get() };
+        Self::fill_ring(bp, mmaped_ring, self.entry_len.get(), RING_SIZE);
+        self.in_flight.set(0);
+        self.issued.clear();
+        self.sending.clear();
+        self.staged_len.set(0);
+        self.provided.set(RING_SIZE);
+
+        self.register(ring)
+    }
+
+    /// removes the group from the kernel, keeping the ring and the pool mapped.
+    /// buffers you hold stay valid, unregistering twice is a no-op.
+    pub fn unregister(&self, ring: &IoUring) -> std::io::Result<()> {
+        if !self.registered.get() {
+            return Ok(());
+        }
+        ring.submitter().unregister_buf_ring(self.id)?;
+        self.registered.set(false);
+        Ok(())
+    }
+
+    /// registers the group again after `unregister`, possibly on another ring or with other flags.
+    /// the kernel head restarts at 0, so every provided buffer you don't hold is published again.
+    pub fn reregister(&self, ring: &IoUring, flags: u16) -> std::io::Result<()> {
+        if self.registered.get() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                "buffer ring is still registered",
+            ));
+        }
+        let pool = unsafe { &*self.buffer_pool.get() };
+        let ring_entries = unsafe { &*self.mapped_ring.get() };
+        unsafe {
+            let ring_ptr = ring_entries.inner().as_ptr();
+            let mut tail = 0u16;
+            for bid in
+                (0..RING_SIZE).filter(|&bid| self.owns_bid(bid) && !self.issued.contains(bid))
+            {
+                setup_ring_entry::<RING_SIZE>(
+                    ring_ptr,
+                    tail,
+                    pool.ptr_for_bid(bid) as u64,
+                    self.entry_len.get(),
+                    bid,
+                );
+                tail += 1;
+            }
+            set_tail(ring_ptr, tail);
+        }
+        self.flags.set(flags);
+        self.register(ring)?;
+        // the group id is now taken on `ring`, and freed on the one it was registered on before
+        if let Some(old_fd) = self.ring_fd.replace(Some(ring.as_raw_fd())) {
+            GroupIdAllocator::release(old_fd, self.id);
+        }
+        GroupIdAllocator::claim(ring.as_raw_fd(), self.id);
+        Ok(())
+    }
+
+    /// moves the group to a bigger ring of NEW_RING_SIZE buffers, under the same group id.
+    /// the ring has to be quiesced: no buffer held, send buffers included, and no recv in flight on the
+    /// group, the old pool is unmapped. the new ring keeps the flags, entry len, low water mark and
+    /// recycle hook, and starts with every buffer provided.
+    /// on failure the old ring is registered again and handed back with the error, boxed so the
+    /// Result stays the size of the new ring.
+    pub fn grow<const NEW_RING_SIZE: u16>(
+        self,
+        ring: &IoUring,
+    ) -> Result<RingBuffer<BUFFER_SIZE, NEW_RING_SIZE>, Box<(Self, std::io::Error)>> {
+        if let Err(e) = self.reject_shared_pool("grow") {
+            return Err(Box::new((self, e)));
+        }
+        // send buffers hold pointers into the old pool too, not only the received ones
+        if NEW_RING_SIZE < RING_SIZE || !self.issued.is_empty() {
+            let reason = match self.issued.is_empty() {
+                true => "the new ring is smaller than the current one",
+                false => "buffers are still in flight",
+            };
+            let error = std::io::Error::new(std::io::ErrorKind::InvalidInput, reason);
+            return Err(Box::new((self, error)));
+        }
+        let was_registered = self.registered.get();
+        if let Err(e) = self.unregister(ring) {
+            return Err(Box::new((self, e)));
+        }
+        let grown = RingBufferBuilder::<BUFFER_SIZE, NEW_RING_SIZE>::new(self.id)
+            .flags(self.flags.get())
+            .entry_len(self.entry_len.get())
+            .low_water_mark(self.low_water_mark)
+            .max_outstanding_bytes(self.max_outstanding_bytes)
+            .recorder_rc(self.recorder.clone())
+            .build(ring);
+        match grown {
+            Ok(grown) => {
+                *grown.on_recycle.borrow_mut() = self.on_recycle.take();
+                // dropping the old ring releases the group id the new one just claimed
+                drop(self);
+                if let Some(ring_fd) = grown.ring_fd.get() {
+                    GroupIdAllocator::claim(ring_fd, grown.id);
+                }
+                Ok(grown)
+            }
+            Err(e) => {
+                if was_registered {
+                    let _ = self.reregister(ring, self.flags.get());
+                }
+                Err(Box::new((self, e)))
+            }
+        }
+    }
+
+    pub fn is_registered(&self) -> bool {
+        self.registered.get()
+    }
+
+    fn register(&self, ring: &IoUring) -> std::io::Result<()> {
+        let mmaped_ring = unsafe { &*self.mapped_ring.get() };
+        unsafe {
+            ring.submitter().register_buf_ring_with_flags(
+                mmaped_ring.inner().as_ptr() as _,
+                RING_SIZE as _,
+                self.id,
+                self.flags.get(),
+            )?
+        };
+        self.registered.set(true);
+        Ok(())
+    }
+
     pub fn get_buffer(&self, bid: BufferId, len: usize) -> Option<Buffer<BUFFER_SIZE>> {
         let inner = unsafe { &*self.buffer_pool.get() };
         if len > BUFFER_SIZE as usize {
             return None;
         }
-        inner.get(bid).map(|ptr| Buffer {
+        let buffer = inner.get(bid).map(|ptr| Buffer {
             bid,
             ptr,
             len,
             _not_send_sync: PhantomData,
+        })?;
+        trace_event!(trace, group_id = self.id, bid, len, "buffer taken");
+        // an empty completion didn't consume the buffer, recycling it is a no-op so its bid
+        // doesn't end up twice in the ring
+        if len != 0 && self.mark_issued(bid) {
+            self.record(|r| {
+                r.on_issue(1);
+                r.on_bytes(len);
+            });
+        }
+        Some(buffer)
+    }
+
+    /// gets the `len` bytes received starting at buffer `bid`, possibly spread over several buffers.
+    /// assumes the buffers were recycled in order, so the ring hands them out with consecutive bids.
+    /// None if bid is out of range or len exceeds the whole pool.
+    pub fn get_buffers_range(&self, bid: BufferId, len: usize) -> Option<BufferRange<BUFFER_SIZE>> {
+        let inner = unsafe { &*self.buffer_pool.get() };
+        if len > BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE {
+            return None;
+        }
+        let range = Self::range_in_pool(inner.get(0)?, inner.get(bid)?, bid, len);
+        Some(self.take_range(range))
+    }
+
+    /// copies the `len` bytes received starting at buffer `bid` out and recycles the buffers
+    /// right away, for when a copy is cheaper than managing the range. None like get_buffers_range.
+    pub fn take_owned(&self, bid: BufferId, len: usize) -> Option<Vec<u8>> {
+        let range = self.get_buffers_range(bid, len)?;
+        let data = Vec::from(&range);
+        self.recycle_buffers_range(&range);
+        Some(data)
+    }
+
+    /// same as get_buffers_range, but refuses with a WouldBlock `RingBufferError::Backpressure`
+    /// when the buffers held by the app would exceed the builder's max_outstanding_bytes.
+    /// the data stays in the ring buffers, take it again once some ranges are recycled.
+    pub fn try_get_buffers_range(
+        &self,
+        bid: BufferId,
+        len: usize,
+    ) -> std::io::Result<BufferRange<BUFFER_SIZE>> {
+        let outstanding =
+            (self.in_flight.get() as usize + Self::slot_count(len) as usize) * BUFFER_SIZE as usize;
+        if outstanding > self.max_outstanding_bytes {
+            return Err(RingBufferError::Backpressure {
+                outstanding,
+                max: self.max_outstanding_bytes,
+            }
+            .into());
+        }
+        self.get_buffers_range(bid, len).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{len} bytes from bid {bid} out of the pool"),
+            )
+        })
+    }
+
+    /// same as get_buffers_range, but also checks every buffer the range covers is owned by the
+    /// kernel: provided and not held by the app. a bid out of the ring or a buffer already taken
+    /// (e.g. a double completion) fails with an InvalidData `RingBufferError::UnexpectedBid`
+    /// instead of reading stale data. meant for bring-up on new kernels.
+    pub fn get_buffers_range_checked(
+        &self,
+        bid: BufferId,
+        len: usize,
+    ) -> std::io::Result<BufferRange<BUFFER_SIZE>> {
+        if bid >= RING_SIZE {
+            return Err(RingBufferError::UnexpectedBid(bid).into());
+        }
+        if len > BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{len} bytes from bid {bid} out of the pool"),
+            ));
+        }
+        if let Some(unexpected) = (0..Self::slot_count(len))
+            .map(|i| bid.wrapping_add(i) & (RING_SIZE - 1))
+            .find(|&b| !self.owns_bid(b) || self.issued.contains(b))
+        {
+            return Err(RingBufferError::UnexpectedBid(unexpected).into());
+        }
+        self.get_buffers_range(bid, len).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{len} bytes from bid {bid} out of the pool"),
+            )
+        })
+    }
+
+    /// same as get_buffers_range without the bound checks, for the completion hot path.
+    ///
+    /// # Safety
+    /// `bid` and `len` must be the ones the kernel reported in a CQE of this group:
+    /// `bid` below RING_SIZE and `len` no bigger than the whole pool.
+    pub unsafe fn get_buffers_range_unchecked(
+        &self,
+        bid: BufferId,
+        len: usize,
+    ) -> BufferRange<BUFFER_SIZE> {
+        debug_assert!(bid < RING_SIZE && len <= BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE);
+        let inner = unsafe { &*self.buffer_pool.get() };
+        let range = unsafe {
+            Self::range_in_pool(inner.get_unchecked(0), inner.get_unchecked(bid), bid, len)
+        };
+        self.take_range(range)
+    }
+
+    /// marks the buffers of a range just handed out as issued
+    fn take_range(&self, range: BufferRange<BUFFER_SIZE>) -> BufferRange<BUFFER_SIZE> {
+        let issued = self
+            .buffer_ids_of(&range)
+            .filter(|&bid| self.mark_issued(bid))
+            .count();
+        self.record(|r| {
+            r.on_issue(issued as u16);
+            r.on_bytes(range.len());
+        });
+        trace_event!(
+            trace,
+            group_id = self.id,
+            len = range.len(),
+            "buffers range taken"
+        );
+        range
+    }
+
+    /// splits `len` bytes starting at buffer `bid` (at `first_ptr`) of the pool starting at `base`.
+    /// `bid` must be in the ring and `len` must fit in the pool.
+    fn range_in_pool(
+        base: NonNull<u8>,
+        first_ptr: NonNull<u8>,
+        bid: BufferId,
+        len: usize,
+    ) -> BufferRange<BUFFER_SIZE> {
+        // len == 0 is checked first, last_buffer_index would underflow
+        let (first, second) = if len == 0 || Self::last_buffer_index(bid, len) < RING_SIZE as u32 {
+            (BufferRangeInner::new(first_ptr, len), None)
+        } else {
+            let first_len = (RING_SIZE - bid) as usize * BUFFER_SIZE as usize;
+            (
+                BufferRangeInner::new(first_ptr, first_len),
+                Some(BufferRangeInner::new(base, len - first_len)),
+            )
+        };
+        BufferRange {
+            first_bid: bid,
+            first,
+            second,
+            _not_send_sync: PhantomData,
+        }
+    }
+
+    /// false if the buffer was already issued
+    fn mark_issued(&self, bid: BufferId) -> bool {
+        if !self.issued.insert(bid) {
+            return false;
+        }
+        self.in_flight.set(self.in_flight.get() + 1);
+        self.peak_in_flight
+            .set(self.peak_in_flight.get().max(self.in_flight.get()));
+        if self.fill_estimate() == self.low_water_mark {
+            trace_event!(
+                warn,
+                group_id = self.id,
+                low_water_mark = self.low_water_mark,
+                "buffer ring reached its low water mark"
+            );
+            self.record(|r| r.on_starvation());
+        }
+        true
+    }
+
+    fn record(&self, f: impl FnOnce(&dyn BufRingRecorder)) {
+        if let Some(recorder) = &self.recorder {
+            f(recorder.as_ref());
+        }
+    }
+
+    fn mark_recycled(&self, bid: BufferId) {
+        if self.issued.remove(bid) && !self.sending.remove(bid) {
+            self.in_flight.set(self.in_flight.get() - 1);
+        }
+    }
+
+    /// true if `bid` is one of the group's receive buffers: provided, or moved in when sharing
+    /// another ring's pool
+    fn owns_bid(&self, bid: BufferId) -> bool {
+        match self.shared_pool {
+            true => self.received.contains(bid),
+            false => bid < self.provided.get(),
+        }
+    }
+
+    /// number of receive buffers of the group, see owns_bid
+    fn recv_buffers(&self) -> u16 {
+        match self.shared_pool {
+            true => self.received_count.get(),
+            false => self.provided.get(),
+        }
+    }
+
+    /// `bid` was moved into this ring by recycle_range_into
+    fn receive_bid(&self, bid: BufferId) {
+        if self.shared_pool && self.received.insert(bid) {
+            self.received_count.set(self.received_count.get() + 1);
+        }
+    }
+
+    /// `bid` was moved out of this ring by recycle_range_into
+    fn release_bid(&self, bid: BufferId) {
+        if self.shared_pool && self.received.remove(bid) {
+            self.received_count.set(self.received_count.get() - 1);
+        }
+    }
+
+    /// the methods handing out or publishing bids by position in the pool can't tell which of
+    /// them the owner of a shared pool gave to its own group
+    fn reject_shared_pool(&self, method: &str) -> std::io::Result<()> {
+        if self.shared_pool {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{method} isn't available on a ring sharing another ring's pool"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// number of ring slots (buffers) a completion of `len` bytes consumed, saturating at u16::MAX
+    pub fn slots_for(&self, len: usize) -> u16 {
+        Self::slot_count(len)
+    }
+
+    /// number of buffers get_buffers_range(bid, len) would cover, without building the range.
+    /// 0 where get_buffers_range returns None (bid out of the ring, len over the pool).
+    pub fn range_slot_count(&self, bid: BufferId, len: usize) -> u16 {
+        if bid >= RING_SIZE || len > BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE {
+            return 0;
+        }
+        Self::slot_count(len)
+    }
+
+    fn slot_count(len: usize) -> u16 {
+        len.div_ceil(BUFFER_SIZE as usize).min(u16::MAX as usize) as u16
+    }
+
+    /// index of the last buffer `len` bytes from `bid` land in, not wrapped at RING_SIZE.
+    /// None for len == 0 (no buffer) or when the index doesn't fit a u16.
+    pub fn checked_last_buffer_index(bid: BufferId, len: usize) -> Option<u16> {
+        if len == 0 {
+            return None;
+        }
+        u16::try_from(Self::last_buffer_index(bid, len)).ok()
+    }
+
+    /// same as checked_last_buffer_index computed in u32, saturating instead of overflowing.
+    /// len must be nonzero
+    fn last_buffer_index(bid: BufferId, len: usize) -> u32 {
+        let count = len.div_ceil(BUFFER_SIZE as usize).min(u32::MAX as usize) as u32;
+        (bid as u32).saturating_add(count - 1)
+    }
+
+    /// ids of the buffers covered by a segment, from its position in the pool
+    fn get_range_inner(&self, inner: &BufferRangeInner) -> Range<BufferId> {
+        let pool = unsafe { &*self.buffer_pool.get() };
+        Self::bids_in_pool(pool.ptr_for_bid(0), inner)
+    }
+
+    /// ids of the buffers covered by a segment of the pool starting at `base`.
+    /// keyed off the BUFFER_SIZE stride, whatever length is advertised to the kernel
+    fn bids_in_pool(base: *const u8, inner: &BufferRangeInner) -> Range<BufferId> {
+        let start = (inner.ptr.as_ptr() as usize - base as usize) / BUFFER_SIZE as usize;
+        let count = inner.covered.div_ceil(BUFFER_SIZE as usize);
+        start as BufferId..(start + count) as BufferId
+    }
+
+    /// ids of the buffers covered by a range, the second item is Some if the range wrapped
+    fn get_range(
+        &self,
+        range: &BufferRange<BUFFER_SIZE>,
+    ) -> (Range<BufferId>, Option<Range<BufferId>>) {
+        (
+            self.get_range_inner(&range.first),
+            range.second.as_ref().map(|s| self.get_range_inner(s)),
+        )
+    }
+
+    /// publishes the next `count` buffers never handed to the kernel yet (after `new_empty` or
+    /// `new_with_initial`),
+    /// returns how many were actually published, less than count once the pool is exhausted.
+    /// fails with InvalidInput on a ring sharing another's pool.
+    pub fn provide(&self, count: u16) -> std::io::Result<u16> {
+        self.reject_shared_pool("provide")?;
+        let pool = unsafe { &*self.buffer_pool.get() };
+        let ring = unsafe { &*self.mapped_ring.get() };
+        let first = self.provided.get();
+        // stop before a buffer checked out for sending
+        let available = (first..RING_SIZE)
+            .find(|&bid| self.issued.contains(bid))
+            .unwrap_or(RING_SIZE)
+            - first;
+        let count = count.min(available);
+
+        unsafe {
+            let ring_ptr = ring.inner().as_ptr();
+            let tail = get_tail(ring_ptr);
+            for i in 0..count {
+                let bid = first + i;
+                setup_ring_entry::<RING_SIZE>(
+                    ring_ptr,
+                    tail.wrapping_add(i),
+                    pool.ptr_for_bid(bid) as u64,
+                    self.entry_len.get(),
+                    bid,
+                );
+            }
+            set_tail(ring_ptr, tail.wrapping_add(count));
+        }
+        self.provided.set(first + count);
+        Ok(count)
+    }
+
+    /// number of buffers published to the kernel at least once, RING_SIZE unless built empty or
+    /// with a smaller `initial`
+    pub fn provided(&self) -> u16 {
+        self.provided.get()
+    }
+
+    /// checks out a buffer the kernel was never given (see `new_empty`/`provide`) to send `len` bytes.
+    /// buffers are taken from the end of the pool, `provide` stops before them.
+    /// fails with InvalidInput if len > BUFFER_SIZE or the ring shares another's pool, with
+    /// ENOBUFS if no unprovided buffer is free.
+    pub fn acquire_send_buffer(&self, len: usize) -> std::io::Result<BufferMut<BUFFER_SIZE>> {
+        self.reject_shared_pool("acquire_send_buffer")?;
+        let pool = unsafe { &*self.buffer_pool.get() };
+        if len > BUFFER_SIZE as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{len} bytes don't fit in a buffer of {BUFFER_SIZE}"),
+            ));
+        }
+        let bid = (self.provided.get()..RING_SIZE)
+            .rev()
+            .find(|&bid| !self.issued.contains(bid))
+            .ok_or(rustix::io::Errno::NOBUFS)?;
+        let ptr = unsafe { NonNull::new_unchecked(pool.ptr_for_bid(bid)) };
+        // the slot was never provided, it doesn't count against in_flight nor fill_estimate
+        self.issued.insert(bid);
+        self.sending.insert(bid);
+        Ok(BufferMut {
+            ptr,
+            len,
+            pos: 0,
+            bid,
+            _not_send_sync: PhantomData,
         })
     }
+
+    /// Send SQE for exactly the bytes of `buffer`, keep the buffer alive until its CQE
+    pub fn send_from(&self, fd: Fd, buffer: &BufferMut<BUFFER_SIZE>) -> squeue::Entry {
+        opcode::Send::new(fd, buffer.ptr.as_ptr(), buffer.len as u32).build()
+    }
+
+    /// gives a send buffer back to the pool, call it once the send CQE arrived
+    pub fn release_send_buffer(&self, buffer: BufferMut<BUFFER_SIZE>) {
+        self.mark_recycled(buffer.bid);
+    }
+
+    /// copies `data` at the start of buffer `bid`, to pre-load content served many times.
+    /// the buffer must not be owned by the kernel: either never provided (see `new_empty`) or
+    /// currently held by the app. typical pattern for a static file server: build the ring
+    /// `empty`, `provide` the recv buffers from the front, fill slots at the end of the pool once,
+    /// then send them as often as needed with `send_slot`. don't mix with acquire_send_buffer,
+    /// which hands out the same slots.
+    /// fails with InvalidInput on a ring sharing another's pool.
+    pub fn fill_buffer(&self, bid: BufferId, data: &[u8]) -> std::io::Result<()> {
+        self.reject_shared_pool("fill_buffer")?;
+        if bid >= RING_SIZE || data.len() > BUFFER_SIZE as usize {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} bytes don't fit in buffer {bid}", data.len()),
+            ));
+        }
+        if bid < self.provided.get() && !self.issued.contains(bid) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ResourceBusy,
+                format!("buffer {bid} is provided to the kernel"),
+            ));
+        }
+        let pool = unsafe { &*self.buffer_pool.get() };
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), pool.ptr_for_bid(bid), data.len());
+        }
+        Ok(())
+    }
+
+    /// Send SQE for the first `len` bytes of a buffer pre-loaded with `fill_buffer`.
+    /// None if `bid` is out of the ring or `len` > BUFFER_SIZE.
+    pub fn send_slot(&self, fd: Fd, bid: BufferId, len: usize) -> Option<squeue::Entry> {
+        if len > BUFFER_SIZE as usize {
+            return None;
+        }
+        let pool = unsafe { &*self.buffer_pool.get() };
+        let ptr = pool.get(bid)?;
+        Some(opcode::Send::new(fd, ptr.as_ptr(), len as u32).build())
+    }
+
+    /// ids of the buffers covered by a range in ring order, continuing from 0 if it wrapped
+    pub fn buffer_ids_of(
+        &self,
+        range: &BufferRange<BUFFER_SIZE>,
+    ) -> impl Iterator<Item = BufferId> + use<BUFFER_SIZE, RING_SIZE> {
+        let (first, second) = self.get_range(range);
+        first.chain(second.into_iter().flatten())
+    }
+
+    ///recycles every buffer of a range in order, use this only once on a range when you are done
+    pub fn recycle_buffers_range(&self, range: &BufferRange<BUFFER_SIZE>) {
+        for bid in self.buffer_ids_of(range) {
+            self.recycle_bid(bid);
+        }
+        trace_event!(
+            trace,
+            group_id = self.id,
+            count = self.buffer_ids_of(range).count(),
+            "buffers range recycled"
+        );
+    }
+
+    /// recycles a range acquired from this ring into `target`'s ring, for rings (e.g. one per
+    /// io_uring) backed by the same pool memory, see `new_sharing_pool`. the buffers are published on `target`'s tail and
+    /// no longer counted as issued here. fails with InvalidInput, recycling nothing, if the two
+    /// pools don't start at the same address.
+    pub fn recycle_range_into(
+        &self,
+        target: &RingBuffer<BUFFER_SIZE, RING_SIZE>,
+        range: &BufferRange<BUFFER_SIZE>,
+    ) -> std::io::Result<()> {
+        let (pool, target_pool) = unsafe { (self.pool_ptr(), target.pool_ptr()) };
+        if pool != target_pool {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "group {} and group {} don't share their buffer pool",
+                    self.id, target.id
+                ),
+            ));
+        }
+        for bid in self.buffer_ids_of(range) {
+            target.receive_bid(bid);
+            target.recycle_bid(bid);
+            self.mark_recycled(bid);
+            self.release_bid(bid);
+        }
+        Ok(())
+    }
+
+    /// recycles the buffers a completion of `len` bytes starting at `bid` consumed, the same ones
+    /// get_buffers_range(bid, len) covers, for callers that didn't keep the BufferRange around.
+    pub fn recycle_by_id_range(&self, bid: BufferId, len: usize) -> std::io::Result<()> {
+        if bid >= RING_SIZE || len > BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{len} bytes from bid {bid} out of the pool"),
+            ));
+        }
+        for i in 0..Self::slot_count(len) {
+            self.recycle_bid(bid.wrapping_add(i) & (RING_SIZE - 1));
+        }
+        Ok(())
+    }
+
+    /// stages the buffer to be recycled by the next flush, instead of publishing it right away.
+    /// the staging area holds a whole ring, it can't fill up unless a buffer is staged twice,
+    /// in which case it is flushed first.
+    pub fn defer_recycle(&self, buffer: &Buffer<BUFFER_SIZE>) {
+        self.stage(buffer.bid);
+    }
+
+    /// defer_recycle for every buffer of a range
+    pub fn defer_recycle_range(&self, range: &BufferRange<BUFFER_SIZE>) {
+        for bid in self.buffer_ids_of(range) {
+            self.stage(bid);
+        }
+    }
+
+    fn stage(&self, bid: BufferId) {
+        debug_assert!(bid < RING_SIZE, "staging bid {bid} out of the ring");
+        if self.staged_len.get() == RING_SIZE {
+            self.flush();
+        }
+        let len = self.staged_len.get();
+        self.staged[len as usize].set(bid);
+        self.staged_len.set(len + 1);
+    }
+
+    /// publishes every staged buffer with a single tail store, returns how many were recycled.
+    /// an on_recycle hook must not defer recycles while it runs from a flush.
+    pub fn flush(&self) -> u16 {
+        let count = self.staged_len.get();
+        if count == 0 {
+            return 0;
+        }
+        let pool = unsafe { &*self.buffer_pool.get() };
+        let ring = unsafe { &*self.mapped_ring.get() };
+        unsafe {
+            let ring_ptr = ring.inner().as_ptr();
+            let tail = get_tail(ring_ptr);
+            for (i, slot) in self.staged[..count as usize].iter().enumerate() {
+                let bid = slot.get();
+                self.run_on_recycle(bid);
+                setup_ring_entry::<RING_SIZE>(
+                    ring_ptr,
+                    tail.wrapping_add(i as u16),
+                    pool.ptr_for_bid(bid) as u64,
+                    self.entry_len.get(),
+                    bid,
+                );
+                self.mark_recycled(bid);
+            }
+            set_tail(ring_ptr, tail.wrapping_add(count));
+        }
+        self.staged_len.set(0);
+        self.record(|r| r.on_recycle(count));
+        trace_event!(trace, group_id = self.id, count, "staged buffers flushed");
+        count
+    }
+
+    /// returns every buffer the app still holds to the ring with one tail store, e.g. when
+    /// tearing a connection down. send buffers are released instead. returns how many buffers
+    /// were recycled. every Buffer and BufferRange still around is invalidated: their slots are
+    /// re-armed and the kernel may overwrite them at once.
+    pub fn recycle_all_outstanding(&self) -> u16 {
+        // whatever is already staged is issued too, publish it first so nothing goes out twice
+        let staged = self.flush();
+        for bid in self.issued.iter() {
+            match self.sending.contains(bid) {
+                true => self.mark_recycled(bid),
+                false => self.stage(bid),
+            }
+        }
+        staged + self.flush()
+    }
+
     ///recycles a buffer in the ring, use this only once on a buffer when you are done
     pub fn recycle_buffer(&self, buffer: &Buffer<BUFFER_SIZE>) {
+        self.recycle_buffer_with_len(buffer, self.entry_len.get());
+    }
+
+    /// same as recycle_buffer, consuming the buffer so it can't be read or recycled again
+    pub fn recycle_owned(&self, buffer: Buffer<BUFFER_SIZE>) {
+        self.recycle_buffer(&buffer);
+    }
+
+    ///same as recycle_buffer, but refuses a buffer whose bid is out of the ring
+    pub fn try_recycle_buffer(&self, buffer: &Buffer<BUFFER_SIZE>) -> std::io::Result<()> {
+        if buffer.bid >= RING_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("bid {} out of a ring of {RING_SIZE}", buffer.bid),
+            ));
+        }
+        self.recycle_buffer(buffer);
+        Ok(())
+    }
+
+    /// length advertised to the kernel by the next recycles (and provide, reset, reregister),
+    /// clamped to BUFFER_SIZE, e.g. to shrink what a completion delivers. this is not the stride:
+    /// buffers stay BUFFER_SIZE apart in the pool and bids are still found from that stride.
+    /// entries already published keep the length they had.
+    pub fn set_advertised_len(&self, len: u32) {
+        self.entry_len.set(len.min(BUFFER_SIZE));
+    }
+
+    /// length currently advertised to the kernel per entry, BUFFER_SIZE unless changed
+    pub fn advertised_len(&self) -> u32 {
+        self.entry_len.get()
+    }
+
+    /// runs `f` with the bid of every recycled buffer, before it is handed back to the kernel.
+    /// it runs on the thread recycling, a recycle made from inside `f` doesn't trigger it again.
+    pub fn set_on_recycle(&self, f: impl FnMut(BufferId) + 'static) {
+        *self.on_recycle.borrow_mut() = Some(Box::new(f));
+    }
+
+    pub fn clear_on_recycle(&self) {
+        self.on_recycle.borrow_mut().take();
+    }
+
+    fn run_on_recycle(&self, bid: BufferId) {
+        // taken out while it runs so a recycle from the callback doesn't borrow it twice
+        let Some(mut f) = self.on_recycle.borrow_mut().take() else {
+            return;
+        };
+        f(bid);
+        let mut slot = self.on_recycle.borrow_mut();
+        if slot.is_none() {
+            *slot = Some(f);
+        }
+    }
+
+    ///recycles a buffer in the ring advertising only `len` bytes to the kernel (clamped to BUFFER_SIZE)
+    pub fn recycle_buffer_with_len(&self, buffer: &Buffer<BUFFER_SIZE>, len: u32) {
+        if buffer.len == 0 {
+            return;
+        }
+        self.recycle_inner(buffer.ptr.as_ptr(), buffer.bid, len);
+    }
+
+    /// re-publishes only the part of the buffer from `offset`, advertising `len` bytes, e.g. the
+    /// unused remainder of a buffer partially consumed in incremental (IOU_PBUF_RING_INC) mode.
+    /// fails if `offset + len` goes past BUFFER_SIZE.
+    pub fn recycle_buffer_at(
+        &self,
+        buffer: &Buffer<BUFFER_SIZE>,
+        offset: u32,
+        len: u32,
+    ) -> std::io::Result<()> {
+        if buffer.bid >= RING_SIZE || offset.checked_add(len).is_none_or(|end| end > BUFFER_SIZE) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{len} bytes at offset {offset} out of buffer {}",
+                    buffer.bid
+                ),
+            ));
+        }
+        if buffer.len == 0 {
+            return Ok(());
+        }
+        let pool = unsafe { &*self.buffer_pool.get() };
+        let ptr = unsafe { pool.ptr_for_bid(buffer.bid).add(offset as usize) };
+        self.recycle_inner(ptr, buffer.bid, len);
+        Ok(())
+    }
+
+    /// recycles the buffer only if it is currently issued, false (and the buffer left alone) when
+    /// it isn't: a duplicate recycle or a bid out of the ring. leaks a buffer rather than
+    /// publishing a slot the kernel may still be writing into.
+    pub fn recycle_buffer_saturating(&self, buffer: &Buffer<BUFFER_SIZE>) -> bool {
+        if buffer.bid >= RING_SIZE || !self.issued.contains(buffer.bid) {
+            trace_event!(
+                warn,
+                group_id = self.id,
+                bid = buffer.bid,
+                "ignored recycle of a buffer not in flight"
+            );
+            return false;
+        }
+        self.recycle_buffer(buffer);
+        true
+    }
+
+    ///recycles the buffer of index bid, for callers that don't hold the Buffer anymore
+    pub(crate) fn recycle_bid(&self, bid: BufferId) {
+        let inner = unsafe { &*self.buffer_pool.get() };
+        self.recycle_inner(inner.ptr_for_bid(bid), bid, self.entry_len.get());
+    }
+
+    fn recycle_inner(&self, ptr: *mut u8, bid: BufferId, len: u32) {
+        debug_assert!(bid < RING_SIZE, "recycling bid {bid} out of the ring");
+        self.run_on_recycle(bid);
         let ring = unsafe { &*self.mapped_ring.get() };
 
         unsafe {
             let ring_ptr = ring.inner().as_ptr();
             let tail = get_tail(ring_ptr);
-            setup_ring_entry::<BUFFER_SIZE, RING_SIZE>(
-                ring_ptr,
-                tail,
-                buffer.ptr.as_ptr() as u64,
-                buffer.bid,
-            );
+            setup_ring_entry::<RING_SIZE>(ring_ptr, tail, ptr as u64, len.min(BUFFER_SIZE), bid);
             set_tail(ring_ptr, tail.wrapping_add(1));
         }
+        self.mark_recycled(bid);
+        self.record(|r| r.on_recycle(1));
+        trace_event!(trace, group_id = self.id, bid, "buffer recycled");
+    }
+
+    /// forwards a madvise hint over the pool region, e.g. Advice::LinuxDontFork
+    /// so a forked child doesn't share the recv buffers.
+    pub fn advise(&self, advice: Advice) -> std::io::Result<()> {
+        let pool = unsafe { &*self.buffer_pool.get() };
+        pool.advise(advice)
+    }
+
+    /// changes the protection of the pool, e.g. READ only before handing it to a sandboxed reader.
+    /// the group must be unregistered to drop WRITE, the kernel could not recv into it anymore.
+    pub fn protect_pool(&self, prot: ProtFlags) -> std::io::Result<()> {
+        if self.registered.get() && !prot.contains(ProtFlags::WRITE) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the pool of a registered group must stay writable",
+            ));
+        }
+        unsafe { &*self.buffer_pool.get() }.protect(prot)
+    }
+
+    /// asks the kernel to bring the pool pages back in memory (MADV_WILLNEED),
+    /// call it from a warmup routine to avoid page faults on the first recvs of a burst.
+    pub fn prefault(&self) -> std::io::Result<()> {
+        let pool = unsafe { &*self.buffer_pool.get() };
+        pool.advise(Advice::WillNeed)
+    }
+
+    /// faults in the pages of buffers `bids` (MADV_POPULATE_WRITE, 5.14+), to warm part of a
+    /// pool built without populate. fails with InvalidInput if `bids` goes past the ring.
+    pub fn prefault_buffers(&self, bids: Range<BufferId>) -> std::io::Result<()> {
+        if bids.start > bids.end || bids.end > RING_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("buffers {bids:?} out of a ring of {RING_SIZE}"),
+            ));
+        }
+        if bids.is_empty() {
+            return Ok(());
+        }
+        let pool = unsafe { &*self.buffer_pool.get() };
+        pool.advise_buffers(bids, Advice::LinuxPopulateWrite)
+    }
+
+    /// base of the buffer pool, buffer `bid` starts at `bid * BUFFER_SIZE`.
+    /// # Safety
+    /// the pointer doesn't outlive the RingBuffer, and buffers owned by the kernel
+    /// (published and not received yet) must not be accessed through it.
+    pub unsafe fn pool_ptr(&self) -> NonNull<u8> {
+        let pool = unsafe { &*self.buffer_pool.get() };
+        unsafe { NonNull::new_unchecked(pool.ptr_for_bid(0)) }
+    }
+
+    /// size in bytes of the buffer pool
+    pub fn pool_len(&self) -> usize {
+        BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE
+    }
+
+    /// the RING_SIZE entries registered to the kernel, the tail overlays the last field of entry 0.
+    /// # Safety
+    /// the pointer doesn't outlive the RingBuffer, and entries or tail must not be written
+    /// out of band: the RingBuffer assumes it is the only producer.
+    pub unsafe fn ring_entries_ptr(&self) -> NonNull<BufRingEntry> {
+        let ring = unsafe { &*self.mapped_ring.get() };
+        ring.inner()
+    }
+
+    /// raw tail published by the app (Acquire load)
+    pub fn tail(&self) -> u16 {
+        let ring = unsafe { &*self.mapped_ring.get() };
+        unsafe { get_tail(ring.inner().as_ptr()) }
+    }
+
+    /// number of received buffers taken with get_buffer and not recycled yet, send buffers aside
+    pub fn in_flight(&self) -> u16 {
+        self.in_flight.get()
+    }
+
+    /// most buffers ever held by the app at once, to see how close the ring came to exhaustion
+    pub fn high_water_mark(&self) -> u16 {
+        self.peak_in_flight.get()
+    }
+
+    /// restarts the high water mark from the buffers held right now, e.g. between load tests
+    pub fn reset_high_water_mark(&self) {
+        self.peak_in_flight.set(self.in_flight.get());
+    }
+
+    /// true if buffer `bid` is checked out by the app (or a send buffer), false past the ring
+    pub fn is_issued(&self, bid: BufferId) -> bool {
+        bid < RING_SIZE && self.issued.contains(bid)
+    }
+
+    /// bids not checked out by get_buffer/get_buffers_range, from the app-side accounting:
+    /// a bid the kernel consumed but that no CQE reported yet still shows up here.
+    pub fn free_bids(&self) -> impl Iterator<Item = BufferId> + '_ {
+        (0..RING_SIZE).filter(|&bid| !self.issued.contains(bid))
+    }
+
+    /// app-side estimate of the buffers available to the kernel: the provided ones minus the ones
+    /// in flight. the kernel head of a ring-mapped buffer group isn't observable, so buffers consumed
+    /// by the kernel but not yet seen in a CQE are still counted as available.
+    pub fn fill_estimate(&self) -> u16 {
+        self.recv_buffers().saturating_sub(self.in_flight.get())
+    }
+
+    /// true once fill_estimate dropped to the low water mark set on the builder,
+    /// time to pause issuing recvs or to provide more buffers before ENOBUFS hits.
+    pub fn is_starving(&self) -> bool {
+        self.fill_estimate() <= self.low_water_mark
+    }
+
+    /// true if fill_estimate is at least `min` buffers, a gate before arming another multishot
+    /// recv on this group with a threshold chosen per call, unlike is_starving
+    pub fn has_capacity(&self, min: u16) -> bool {
+        self.fill_estimate() >= min
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> std::fmt::Debug
+    for RingBuffer<BUFFER_SIZE, RING_SIZE>
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pool = unsafe { &*self.buffer_pool.get() };
+        f.debug_struct("RingBuffer")
+            .field("group_id", &self.id)
+            .field("buffer_size", &BUFFER_SIZE)
+            .field("ring_size", &RING_SIZE)
+            .field(
+                "pool_bytes",
+                &BufferPool::<BUFFER_SIZE, RING_SIZE>::TOTAL_SIZE,
+            )
+            .field(
+                "pool_base",
+                &format_args!("{:#x}", pool.ptr_for_bid(0) as usize),
+            )
+            .field("tail", &self.tail())
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> Drop for RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    fn drop(&mut self) {
+        if let Some(ring_fd) = self.ring_fd.get() {
+            GroupIdAllocator::release(ring_fd, self.id);
+        }
     }
 }
@@ -1,43 +1,89 @@
-use std::{mem::MaybeUninit, ptr::NonNull};
+use std::ptr::NonNull;
 
 use io_uring::types::BufRingEntry;
 use rustix::mm::{MapFlags, ProtFlags, mmap_anonymous};
 
+use crate::{builder::MapOptions, error::RingBufferError, mlock_region};
+
+// the ring is sized and indexed as an array of the kernel's struct io_uring_buf
+// (u64 addr, u32 len, u16 bid, u16 resv), fail the build if the io_uring crate ever drifts from it
+const _: () = {
+    assert!(size_of::<BufRingEntry>() == 16);
+    assert!(align_of::<BufRingEntry>() == align_of::<u64>());
+};
+
 pub struct MmapedRing {
     ptr: NonNull<BufRingEntry>,
     len: usize,
+    /// size of the whole mapping, ring entries plus the trailer if any
+    map_len: usize,
+    locked: bool,
 }
 
 impl Drop for MmapedRing {
     fn drop(&mut self) {
         unsafe {
-            let _ = rustix::mm::munmap(
-                self.ptr.as_ptr().cast(),
-                self.len * size_of::<BufRingEntry>(),
-            );
+            if self.locked {
+                let _ = rustix::mm::munlock(self.ptr.as_ptr().cast(), self.map_len);
+            }
+            let _ = rustix::mm::munmap(self.ptr.as_ptr().cast(), self.map_len);
         }
     }
 }
 
 impl MmapedRing {
-    pub fn build(len: usize) -> std::io::Result<Self> {
-        let ptr = Self::map(len)?;
-        Ok(Self::new(ptr, len))
+    pub fn build(len: usize, lock: bool, options: &MapOptions) -> std::io::Result<Self> {
+        Self::build_with_trailer(len, 0, lock, options)
     }
 
-    fn new(ptr: NonNull<BufRingEntry>, len: usize) -> Self {
-        Self { ptr, len }
+    /// maps the ring entries followed by `trailer` bytes starting on the next page boundary,
+    /// the whole region is owned (and unmapped) by the ring.
+    pub fn build_with_trailer(
+        len: usize,
+        trailer: usize,
+        lock: bool,
+        options: &MapOptions,
+    ) -> std::io::Result<Self> {
+        let map_len = match trailer {
+            0 => len * size_of::<BufRingEntry>(),
+            _ => Self::trailer_offset(len) + trailer,
+        };
+        let ptr = Self::map(map_len, options.map_flags(trailer != 0))?;
+        let mut ring = Self::new(ptr, len, map_len);
+        if lock {
+            unsafe { mlock_region(ptr.as_ptr().cast(), map_len)? };
+            ring.locked = true;
+        }
+        Ok(ring)
     }
 
-    fn map(ring_size: usize) -> std::io::Result<NonNull<BufRingEntry>> {
+    fn new(ptr: NonNull<BufRingEntry>, len: usize, map_len: usize) -> Self {
+        Self {
+            ptr,
+            len,
+            map_len,
+            locked: false,
+        }
+    }
+
+    /// offset of the trailer: the ring entries rounded up to the page size
+    fn trailer_offset(len: usize) -> usize {
+        (len * size_of::<BufRingEntry>()).next_multiple_of(rustix::param::page_size())
+    }
+
+    fn map(map_len: usize, flags: MapFlags) -> std::io::Result<NonNull<BufRingEntry>> {
         let mmaped_ring = unsafe {
             mmap_anonymous(
                 core::ptr::null_mut(),
-                ring_size * size_of::<io_uring::types::BufRingEntry>(),
+                map_len,
                 ProtFlags::READ | ProtFlags::WRITE,
-                MapFlags::PRIVATE | MapFlags::POPULATE,
+                flags,
             )
-        }?;
+        }
+        .map_err(|e| RingBufferError::RingMmap {
+            bytes: map_len,
+            source: e.into(),
+        })?;
 
         unsafe {
             *(BufRingEntry::tail(mmaped_ring.cast::<BufRingEntry>()).cast_mut()) = 0;
@@ -45,11 +91,23 @@ impl MmapedRing {
         Ok(unsafe { NonNull::new_unchecked(mmaped_ring) }.cast())
     }
 
-    pub fn as_slice(&mut self) -> &mut [MaybeUninit<BufRingEntry>] {
-        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr().cast(), self.len) }
+    /// the kernel only registers page aligned rings, mmap gives that for free but a ring carved
+    /// out of other memory may not have it
+    pub fn check_alignment(&self) -> Result<(), RingBufferError> {
+        crate::check_ring_alignment(self.ptr.as_ptr())
     }
 
     pub fn inner(&self) -> NonNull<BufRingEntry> {
         self.ptr
     }
+
+    /// start of the region mapped after the ring entries by `build_with_trailer`
+    pub fn trailer(&self) -> *mut u8 {
+        unsafe {
+            self.ptr
+                .as_ptr()
+                .cast::<u8>()
+                .add(Self::trailer_offset(self.len))
+        }
+    }
 }
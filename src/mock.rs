@@ -0,0 +1,116 @@
+use std::{
+    cell::{Cell, RefCell, UnsafeCell},
+    marker::PhantomData,
+    ptr::NonNull,
+};
+
+use crate::{RingBuffer, buffer::Buffer, buffer_range::BufferRange, buffer_source::BufferSource};
+
+/// BufferSource over a plain heap pool, no IoUring involved.
+/// `inject` plays the kernel: it writes data in the next buffers and returns the (bid, len)
+/// a recv completion would have reported. recycled bids are logged for assertions.
+pub struct MockRing<const BUFFER_SIZE: u32, const RING_SIZE: u16> {
+    pool: Box<[UnsafeCell<u8>]>,
+    next: Cell<u16>,
+    recycled: RefCell<Vec<u16>>,
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> Default for MockRing<BUFFER_SIZE, RING_SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> MockRing<BUFFER_SIZE, RING_SIZE> {
+    const TOTAL_SIZE: usize = BUFFER_SIZE as usize * RING_SIZE as usize;
+
+    pub fn new() -> Self {
+        Self {
+            pool: (0..Self::TOTAL_SIZE).map(|_| UnsafeCell::new(0)).collect(),
+            next: Cell::new(0),
+            recycled: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn base(&self) -> NonNull<u8> {
+        NonNull::new(UnsafeCell::raw_get(self.pool.as_ptr())).unwrap()
+    }
+
+    fn ptr_for_bid(&self, bid: u16) -> Option<NonNull<u8>> {
+        if bid >= RING_SIZE {
+            return None;
+        }
+        Some(unsafe { self.base().add(bid as usize * BUFFER_SIZE as usize) })
+    }
+
+    /// writes `data` in the next buffers, wrapping at the end of the ring like the kernel would,
+    /// and returns the (bid, len) of the matching completion.
+    /// panics if data doesn't fit in the pool.
+    pub fn inject(&self, data: &[u8]) -> (u16, usize) {
+        assert!(
+            data.len() <= Self::TOTAL_SIZE,
+            "injected more than the pool"
+        );
+        let bid = self.next.get();
+        let start = bid as usize * BUFFER_SIZE as usize;
+        let first_len = data.len().min(Self::TOTAL_SIZE - start);
+        unsafe {
+            let base = self.base().as_ptr();
+            base.add(start)
+                .copy_from_nonoverlapping(data.as_ptr(), first_len);
+            base.copy_from_nonoverlapping(data[first_len..].as_ptr(), data.len() - first_len);
+        }
+        let slots = data.len().div_ceil(BUFFER_SIZE as usize);
+        self.next
+            .set(((bid as usize + slots) % RING_SIZE as usize) as u16);
+        (bid, data.len())
+    }
+
+    /// every bid recycled so far, in order
+    pub fn recycled_bids(&self) -> Vec<u16> {
+        self.recycled.borrow().clone()
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> BufferSource<BUFFER_SIZE>
+    for MockRing<BUFFER_SIZE, RING_SIZE>
+{
+    fn get_buffer(&self, bid: u16, len: usize) -> Option<Buffer<BUFFER_SIZE>> {
+        if len > BUFFER_SIZE as usize {
+            return None;
+        }
+        Some(Buffer {
+            ptr: self.ptr_for_bid(bid)?,
+            len,
+            bid,
+            _not_send_sync: PhantomData,
+        })
+    }
+
+    fn get_buffers_range(&self, bid: u16, len: usize) -> Option<BufferRange<BUFFER_SIZE>> {
+        if len > Self::TOTAL_SIZE {
+            return None;
+        }
+        let first_ptr = self.ptr_for_bid(bid)?;
+        Some(RingBuffer::<BUFFER_SIZE, RING_SIZE>::range_in_pool(
+            self.base(),
+            first_ptr,
+            bid,
+            len,
+        ))
+    }
+
+    fn recycle_buffer(&self, buffer: &Buffer<BUFFER_SIZE>) {
+        self.recycled.borrow_mut().push(buffer.bid);
+    }
+
+    fn recycle_buffers_range(&self, range: &BufferRange<BUFFER_SIZE>) {
+        let base = self.base().as_ptr();
+        let mut recycled = self.recycled.borrow_mut();
+        for inner in std::iter::once(&range.first).chain(&range.second) {
+            recycled.extend(RingBuffer::<BUFFER_SIZE, RING_SIZE>::bids_in_pool(
+                base, inner,
+            ));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use crate::recv_result::{RecvResult, classify_recv};
+
+/// re-arm bookkeeping for several multishot recvs sharing one buffer group, keyed by the
+/// user_data of each socket's SQE. the kernel ends a multishot on ENOBUFS or on a completion
+/// without IORING_CQE_F_MORE, the registry tells which socket needs a new SQE then.
+#[derive(Debug, Default)]
+pub struct MultishotRegistry {
+    armed: HashMap<u64, bool>,
+}
+
+impl MultishotRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// records that the multishot recv tagged `user_data` was (re)submitted
+    pub fn arm(&mut self, user_data: u64) {
+        self.armed.insert(user_data, true);
+    }
+
+    /// stops tracking `user_data`, e.g. once its socket is closed
+    pub fn forget(&mut self, user_data: u64) {
+        self.armed.remove(&user_data);
+    }
+
+    pub fn is_armed(&self, user_data: u64) -> bool {
+        self.armed.get(&user_data).copied().unwrap_or(false)
+    }
+
+    /// number of sockets with a multishot recv in flight
+    pub fn armed_count(&self) -> usize {
+        self.armed.values().filter(|&&armed| armed).count()
+    }
+
+    /// updates the state of `user_data` from the `result` and `flags` of its CQE, true if the
+    /// multishot terminated and the socket should be re-armed: data without F_MORE or ENOBUFS.
+    /// a closed socket or an error leaves it disarmed and returns false, so does an unknown
+    /// user_data. call `arm` once the new SQE is pushed.
+    pub fn on_completion(&mut self, user_data: u64, result: i32, flags: u32) -> bool {
+        let Some(armed) = self.armed.get_mut(&user_data) else {
+            return false;
+        };
+        let (still_armed, rearm) = match classify_recv(result, flags) {
+            RecvResult::Data { more: true, .. } => (true, false),
+            RecvResult::Data { more: false, .. } | RecvResult::NoBuffers => (false, true),
+            RecvResult::Closed | RecvResult::Err(_) => (false, false),
+        };
+        *armed = still_armed;
+        rearm
+    }
+
+    /// user_data of every socket whose multishot terminated and wasn't re-armed yet
+    pub fn disarmed(&self) -> impl Iterator<Item = u64> + '_ {
+        self.armed
+            .iter()
+            .filter(|&(_, &armed)| !armed)
+            .map(|(&user_data, _)| user_data)
+    }
+}
@@ -0,0 +1,24 @@
+use io_uring::{opcode, squeue, types::Fd};
+
+use crate::RingBuffer;
+
+/// buffer select isn't only for sockets: IORING_OP_READ picks its buffer from the group too,
+/// on files and pipes alike (5.7+). multishot read (IORING_OP_READ_MULTISHOT, 6.7+) only works
+/// on pollable files (pipes, eventfd, sockets...), regular files need the single-shot `read`.
+/// completions are handled as recvs, with `classify_recv` and `get_buffers_range`.
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    /// single-shot read of up to BUFFER_SIZE bytes at `offset` into a buffer of this group,
+    /// use u64::MAX as `offset` to read at the file position
+    pub fn read(&self, fd: Fd, offset: u64) -> squeue::Entry {
+        opcode::Read::new(fd, std::ptr::null_mut(), BUFFER_SIZE)
+            .offset(offset)
+            .buf_group(self.id)
+            .build()
+            .flags(squeue::Flags::BUFFER_SELECT)
+    }
+
+    /// multishot read posting one CQE per buffer filled, until an error or end of file
+    pub fn read_multi(&self, fd: Fd) -> squeue::Entry {
+        opcode::ReadMulti::new(fd, BUFFER_SIZE, self.id).build()
+    }
+}
@@ -0,0 +1,33 @@
+use std::rc::Rc;
+
+/// receives the counters of a RingBuffer, implement it to forward them to your metrics backend.
+/// called inline on the hot path, keep the implementations cheap.
+pub trait BufRingRecorder {
+    /// `count` buffers were handed to the app
+    fn on_issue(&self, count: u16);
+    /// `count` buffers were published back to the kernel
+    fn on_recycle(&self, count: u16);
+    /// the ring dropped to its low water mark, ENOBUFS is close
+    fn on_starvation(&self);
+    /// a completion delivered `len` bytes
+    fn on_bytes(&self, _len: usize) {}
+}
+
+/// one recorder shared by several rings
+impl<R: BufRingRecorder + ?Sized> BufRingRecorder for Rc<R> {
+    fn on_issue(&self, count: u16) {
+        (**self).on_issue(count)
+    }
+
+    fn on_recycle(&self, count: u16) {
+        (**self).on_recycle(count)
+    }
+
+    fn on_starvation(&self) {
+        (**self).on_starvation()
+    }
+
+    fn on_bytes(&self, len: usize) {
+        (**self).on_bytes(len)
+    }
+}
@@ -0,0 +1,148 @@
+use io_uring::{CompletionQueue, IoUring, cqueue, opcode, types::Fd};
+use rustix::io::Errno;
+
+use crate::{RingBuffer, buffer_range::BufferRange};
+
+const FOR_EACH_USER_DATA: u64 = 0x6561_6368;
+const FOR_EACH_CANCEL_USER_DATA: u64 = 0x6361_6e63;
+
+/// a recv CQE on a buffer group, turned into an exhaustive match instead of magic numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvResult {
+    /// `len` bytes landed starting at buffer `bid`, `more` if the multishot is still armed
+    Data {
+        bid: u16,
+        len: usize,
+        more: bool,
+    },
+    /// result 0: the peer closed the connection
+    Closed,
+    /// ENOBUFS: the group ran out of buffers, the recv has to be resubmitted
+    NoBuffers,
+    Err(Errno),
+}
+
+/// classifies the `result` and `flags` of a recv completion.
+/// a positive result without a selected buffer is reported as `Err(Errno::INVAL)`.
+pub fn classify_recv(result: i32, flags: u32) -> RecvResult {
+    match result {
+        0 => RecvResult::Closed,
+        n if n > 0 => match cqueue::buffer_select(flags) {
+            Some(bid) => RecvResult::Data {
+                bid,
+                len: n as usize,
+                more: cqueue::more(flags),
+            },
+            None => RecvResult::Err(Errno::INVAL),
+        },
+        e if -e == Errno::NOBUFS.raw_os_error() => RecvResult::NoBuffers,
+        e => RecvResult::Err(Errno::from_raw_os_error(-e)),
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    /// classifies the CQEs as they are popped, along with the range each Data completion landed in.
+    /// every CQE is treated as a recv on this group, filter by user_data upstream if needed.
+    pub fn drain_completions<'a>(
+        &'a self,
+        cq: CompletionQueue<'a>,
+    ) -> impl Iterator<Item = (RecvResult, Option<BufferRange<BUFFER_SIZE>>)> + 'a {
+        cq.map(|cqe| {
+            let result = classify_recv(cqe.result(), cqe.flags());
+            let range = match result {
+                RecvResult::Data { bid, len, .. } => self.get_buffers_range(bid, len),
+                _ => None,
+            };
+            (result, range)
+        })
+    }
+
+    /// runs a multishot recv on `fd` until the peer closes the connection, calling `f` with every
+    /// range received and recycling it as soon as `f` returns. the recv is re-armed when the
+    /// kernel ends it (ENOBUFS included). `ring` must not carry other requests meanwhile, their
+    /// CQEs would be dropped. a recv error is returned as is, a completion out of the ring fails
+    /// with InvalidData once the recv is cancelled and its last CQE reaped.
+    pub fn for_each_recv(
+        &self,
+        ring: &mut IoUring,
+        fd: Fd,
+        mut f: impl FnMut(&BufferRange<BUFFER_SIZE>),
+    ) -> std::io::Result<()> {
+        let recv = opcode::RecvMulti::new(fd, self.id)
+            .build()
+            .user_data(FOR_EACH_USER_DATA);
+        let mut armed = false;
+        loop {
+            if !armed {
+                unsafe {
+                    ring.submission()
+                        .push(&recv)
+                        .map_err(|_| std::io::Error::from(std::io::ErrorKind::WouldBlock))?;
+                }
+                armed = true;
+            }
+            ring.submit_and_wait(1)?;
+            let mut invalid = false;
+            for cqe in ring.completion() {
+                if cqe.user_data() != FOR_EACH_USER_DATA {
+                    continue;
+                }
+                if !cqueue::more(cqe.flags()) {
+                    armed = false;
+                }
+                match classify_recv(cqe.result(), cqe.flags()) {
+                    RecvResult::Data { bid, len, .. } => {
+                        let Some(range) = self.get_buffers_range(bid, len) else {
+                            invalid = true;
+                            break;
+                        };
+                        f(&range);
+                        self.recycle_buffers_range(&range);
+                    }
+                    RecvResult::Closed => return Ok(()),
+                    RecvResult::NoBuffers => {}
+                    RecvResult::Err(e) => return Err(e.into()),
+                }
+            }
+            if invalid {
+                if armed {
+                    self.cancel_for_each(ring)?;
+                }
+                return Err(std::io::ErrorKind::InvalidData.into());
+            }
+        }
+    }
+
+    /// cancels the multishot recv of for_each_recv and waits for its last CQE, so it doesn't
+    /// keep consuming buffers once the loop returned. data still landing is recycled unread.
+    fn cancel_for_each(&self, ring: &mut IoUring) -> std::io::Result<()> {
+        let cancel = opcode::AsyncCancel::new(FOR_EACH_USER_DATA)
+            .build()
+            .user_data(FOR_EACH_CANCEL_USER_DATA);
+        if unsafe { ring.submission().push(&cancel) }.is_err() {
+            ring.submit()?;
+            unsafe { ring.submission().push(&cancel) }
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::WouldBlock))?;
+        }
+        let (mut recv_done, mut cancel_done) = (false, false);
+        while !(recv_done && cancel_done) {
+            ring.submit_and_wait(1)?;
+            for cqe in ring.completion() {
+                match cqe.user_data() {
+                    FOR_EACH_CANCEL_USER_DATA => cancel_done = true,
+                    FOR_EACH_USER_DATA => {
+                        recv_done |= !cqueue::more(cqe.flags());
+                        if let RecvResult::Data { bid, len, .. } =
+                            classify_recv(cqe.result(), cqe.flags())
+                            && let Some(range) = self.get_buffers_range(bid, len)
+                        {
+                            self.recycle_buffers_range(&range);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
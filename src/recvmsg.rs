@@ -0,0 +1,96 @@
+use std::{
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+};
+
+use io_uring::{opcode, squeue, types::Fd};
+
+use crate::{RingBuffer, buffer::Buffer};
+
+/// size of struct io_uring_recvmsg_out, the header the kernel writes at the start of the buffer
+const RECVMSG_OUT_LEN: usize = 16;
+
+/// one datagram received by a RecvMsgMulti
+#[derive(Debug)]
+pub struct RecvMsg<'a> {
+    /// source address, None if the name was truncated or isn't an inet address
+    pub addr: Option<SocketAddr>,
+    /// control data (cmsgs), truncated to msg_controllen
+    pub control: &'a [u8],
+    pub payload: &'a [u8],
+    /// the datagram didn't fit in the buffer, payload holds its beginning
+    pub truncated: bool,
+}
+
+/// msghdr for RecvMsgMulti with room for any inet address and `controllen` bytes of control data.
+/// only msg_namelen and msg_controllen are read by the kernel, keep it alive until the last CQE.
+pub fn recvmsg_header(controllen: usize) -> libc::msghdr {
+    let mut msghdr: libc::msghdr = unsafe { std::mem::zeroed() };
+    msghdr.msg_namelen = size_of::<libc::sockaddr_in6>() as _;
+    msghdr.msg_controllen = controllen as _;
+    msghdr
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> RingBuffer<BUFFER_SIZE, RING_SIZE> {
+    /// multishot recvmsg selecting its buffers from this group, `msghdr` must outlive the request
+    pub fn recv_msg_multi(&self, fd: Fd, msghdr: &libc::msghdr) -> squeue::Entry {
+        opcode::RecvMsgMulti::new(fd, msghdr, self.id).build()
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_ne_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+/// splits a buffer filled by RecvMsgMulti: io_uring_recvmsg_out header, then the name area
+/// (msg_namelen bytes), the control area (msg_controllen bytes), then the payload.
+pub fn parse_recvmsg<'a, const SIZE: u32>(
+    buffer: &'a Buffer<SIZE>,
+    msghdr: &libc::msghdr,
+) -> io::Result<RecvMsg<'a>> {
+    let data = buffer.as_ref();
+    let name_area = msghdr.msg_namelen as usize;
+    // msg_controllen is usize on glibc but u32 on musl
+    let control_area: usize = msghdr.msg_controllen as _;
+    let payload_at = RECVMSG_OUT_LEN + name_area + control_area;
+    if data.len() < payload_at {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "buffer shorter than the recvmsg header",
+        ));
+    }
+    let name_len = read_u32(data, 0) as usize;
+    let control_len = read_u32(data, 4) as usize;
+    let payload_len = read_u32(data, 8) as usize;
+
+    let name = &data[RECVMSG_OUT_LEN..RECVMSG_OUT_LEN + name_len.min(name_area)];
+    let control_at = RECVMSG_OUT_LEN + name_area;
+    let payload = &data[payload_at..];
+    Ok(RecvMsg {
+        addr: (name_len <= name_area)
+            .then(|| parse_sockaddr(name))
+            .flatten(),
+        control: &data[control_at..control_at + control_len.min(control_area)],
+        payload: &payload[..payload_len.min(payload.len())],
+        truncated: payload_len > payload.len(),
+    })
+}
+
+/// decodes a sockaddr_in / sockaddr_in6 as written by the kernel
+fn parse_sockaddr(name: &[u8]) -> Option<SocketAddr> {
+    let family = u16::from_ne_bytes(name.get(..2)?.try_into().ok()?) as i32;
+    let port = u16::from_be_bytes(name.get(2..4)?.try_into().ok()?);
+    match family {
+        libc::AF_INET => {
+            let ip: [u8; 4] = name.get(4..8)?.try_into().ok()?;
+            Some(SocketAddrV4::new(Ipv4Addr::from(ip), port).into())
+        }
+        libc::AF_INET6 => {
+            let flowinfo = u32::from_be_bytes(name.get(4..8)?.try_into().ok()?);
+            let ip: [u8; 16] = name.get(8..24)?.try_into().ok()?;
+            let scope_id = u32::from_ne_bytes(name.get(24..28)?.try_into().ok()?);
+            Some(SocketAddrV6::new(Ipv6Addr::from(ip), port, flowinfo, scope_id).into())
+        }
+        _ => None,
+    }
+}
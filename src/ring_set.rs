@@ -0,0 +1,172 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use io_uring::IoUring;
+
+use crate::{RingBuffer, buffer_range::BufferRangeInner};
+
+/// the data of a completion routed through a BufferRingSet, whatever the size of its buffers.
+/// hand it back to `recycle` when you are done, which consumes it so the data can't be read
+/// once the kernel may overwrite it. not automatically returned on Drop.
+#[derive(Debug)]
+pub struct SetRange {
+    group_id: u16,
+    bid: u16,
+    len: usize,
+    first: BufferRangeInner,
+    second: Option<BufferRangeInner>,
+    _not_send_sync: PhantomData<*const ()>,
+}
+
+impl SetRange {
+    /// both segments of the completion, the second one is Some only if it wrapped
+    pub fn as_parts(&self) -> (&[u8], Option<&[u8]>) {
+        (
+            self.first.as_slice(),
+            self.second.as_ref().map(BufferRangeInner::as_slice),
+        )
+    }
+
+    pub fn group_id(&self) -> u16 {
+        self.group_id
+    }
+
+    /// bid of the buffer the completion starts at
+    pub fn first_bid(&self) -> u16 {
+        self.bid
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// object-safe view of a RingBuffer, whatever its BUFFER_SIZE and RING_SIZE
+pub trait AnyRingBuffer {
+    fn group_id(&self) -> u16;
+    fn buffer_size(&self) -> u32;
+    fn ring_size(&self) -> u16;
+    /// the `len` bytes of a completion starting at buffer `bid`, split in two segments like
+    /// `BufferRange::as_parts` when it wraps. None if bid or len is out of range
+    fn data(&self, bid: u16, len: usize) -> Option<SetRange>;
+    /// recycles every buffer `range` covered, fails with InvalidInput if it was taken from
+    /// another group
+    fn recycle(&self, range: SetRange) -> std::io::Result<()>;
+    /// number of buffers handed out by data and not recycled yet
+    fn in_flight(&self) -> u16;
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> AnyRingBuffer
+    for RingBuffer<BUFFER_SIZE, RING_SIZE>
+{
+    fn group_id(&self) -> u16 {
+        self.id
+    }
+
+    fn buffer_size(&self) -> u32 {
+        BUFFER_SIZE
+    }
+
+    fn ring_size(&self) -> u16 {
+        RING_SIZE
+    }
+
+    fn data(&self, bid: u16, len: usize) -> Option<SetRange> {
+        let range = self.get_buffers_range(bid, len)?;
+        Some(SetRange {
+            group_id: self.id,
+            bid,
+            len,
+            first: range.first,
+            second: range.second.filter(|s| s.len > 0),
+            _not_send_sync: PhantomData,
+        })
+    }
+
+    fn recycle(&self, range: SetRange) -> std::io::Result<()> {
+        if range.group_id != self.id {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "range of group {} recycled to group {}",
+                    range.group_id, self.id
+                ),
+            ));
+        }
+        self.recycle_by_id_range(range.bid, range.len)
+    }
+
+    fn in_flight(&self) -> u16 {
+        self.in_flight.get()
+    }
+}
+
+/// several buffer groups registered on one IoUring, torn down together.
+/// owns the ring so every group can be unregistered on drop, before its memory is unmapped.
+pub struct BufferRingSet {
+    ring: IoUring,
+    groups: HashMap<u16, Box<dyn AnyRingBuffer>>,
+}
+
+impl BufferRingSet {
+    pub fn new(ring: IoUring) -> Self {
+        Self {
+            ring,
+            groups: HashMap::new(),
+        }
+    }
+
+    pub fn ring(&self) -> &IoUring {
+        &self.ring
+    }
+
+    pub fn ring_mut(&mut self) -> &mut IoUring {
+        &mut self.ring
+    }
+
+    /// registers a new buffer group on the ring
+    pub fn add<const BUFFER_SIZE: u32, const RING_SIZE: u16>(
+        &mut self,
+        buffer_group_id: u16,
+        flags: u16,
+    ) -> std::io::Result<()> {
+        if self.groups.contains_key(&buffer_group_id) {
+            return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
+        }
+        let rb = RingBuffer::<BUFFER_SIZE, RING_SIZE>::new(&self.ring, flags, buffer_group_id)?;
+        self.groups.insert(buffer_group_id, Box::new(rb));
+        Ok(())
+    }
+
+    pub fn group(&self, buffer_group_id: u16) -> Option<&dyn AnyRingBuffer> {
+        self.groups.get(&buffer_group_id).map(|rb| rb.as_ref())
+    }
+
+    /// routes a completion (group, bid, len) to the ring that owns it, bundles included
+    pub fn data(&self, buffer_group_id: u16, bid: u16, len: usize) -> Option<SetRange> {
+        self.group(buffer_group_id)?.data(bid, len)
+    }
+
+    /// recycles every buffer of a range to the group it was taken from
+    pub fn recycle(&self, range: SetRange) -> std::io::Result<()> {
+        match self.group(range.group_id) {
+            Some(rb) => rb.recycle(range),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no buffer group {} in the set", range.group_id),
+            )),
+        }
+    }
+}
+
+impl Drop for BufferRingSet {
+    fn drop(&mut self) {
+        for (id, rb) in self.groups.drain() {
+            let _ = self.ring.submitter().unregister_buf_ring(id);
+            drop(rb);
+        }
+    }
+}
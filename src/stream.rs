@@ -0,0 +1,157 @@
+use std::{
+    io,
+    os::fd::RawFd,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures_core::Stream;
+use io_uring::{IoUring, cqueue, opcode, squeue, types::Fd};
+use tokio::io::unix::AsyncFd;
+
+use crate::{
+    RingBuffer,
+    buffer_range::BufferRange,
+    recv_result::{RecvResult, classify_recv},
+};
+
+const RECV_USER_DATA: u64 = 0x7374_7265_616d;
+
+/// Stream of the ranges received by a multishot recv on a socket, one item per completion.
+/// owns the IoUring (its fd is polled by tokio to wake on the next CQE) and re-arms the recv
+/// when the kernel ends it. the consumer recycles every range it gets, see `owned` otherwise.
+/// the recv is only re-armed from poll_next: a consumer that stops polling, or holds on to its
+/// ranges until the group runs out of buffers (ENOBUFS), stops the kernel from receiving more.
+pub struct BufferRangeStream<'a, const BUFFER_SIZE: u32, const RING_SIZE: u16> {
+    ring: AsyncFd<IoUring>,
+    buffers: &'a RingBuffer<BUFFER_SIZE, RING_SIZE>,
+    recv: squeue::Entry,
+    armed: bool,
+    eof: bool,
+}
+
+impl<'a, const BUFFER_SIZE: u32, const RING_SIZE: u16>
+    BufferRangeStream<'a, BUFFER_SIZE, RING_SIZE>
+{
+    /// `ring` must be the one `buffers` was registered on and must not be used for anything else.
+    pub fn new(
+        ring: IoUring,
+        buffers: &'a RingBuffer<BUFFER_SIZE, RING_SIZE>,
+        fd: RawFd,
+    ) -> io::Result<Self> {
+        let recv = opcode::RecvMulti::new(Fd(fd), buffers.group_id())
+            .build()
+            .user_data(RECV_USER_DATA);
+        Ok(Self {
+            ring: AsyncFd::new(ring)?,
+            buffers,
+            recv,
+            armed: false,
+            eof: false,
+        })
+    }
+
+    /// turns the stream into one yielding copies of the data, each range recycled right away
+    pub fn owned(self) -> OwnedBufferStream<'a, BUFFER_SIZE, RING_SIZE> {
+        OwnedBufferStream { inner: self }
+    }
+
+    /// gives the ring back, ranges already yielded still have to be recycled
+    pub fn into_inner(self) -> IoUring {
+        self.ring.into_inner()
+    }
+
+    fn arm(&mut self) -> io::Result<()> {
+        let ring = self.ring.get_mut();
+        unsafe {
+            ring.submission()
+                .push(&self.recv)
+                .map_err(|_| io::Error::from(io::ErrorKind::WouldBlock))?;
+        }
+        ring.submit()?;
+        self.armed = true;
+        Ok(())
+    }
+
+    /// handles the next CQE of the recv if there is one, None when the CQ is empty
+    fn next_completion(&mut self) -> Option<io::Result<Option<BufferRange<BUFFER_SIZE>>>> {
+        let cqe = self.ring.get_mut().completion().next()?;
+        if cqe.user_data() != RECV_USER_DATA {
+            return Some(Ok(None));
+        }
+        if !cqueue::more(cqe.flags()) {
+            self.armed = false;
+        }
+        let range = match classify_recv(cqe.result(), cqe.flags()) {
+            RecvResult::Data { bid, len, .. } => Some(
+                self.buffers
+                    .get_buffers_range(bid, len)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData)),
+            ),
+            RecvResult::Closed => {
+                self.eof = true;
+                None
+            }
+            RecvResult::NoBuffers => {
+                trace_event!(
+                    warn,
+                    group_id = self.buffers.group_id(),
+                    "recv starved of buffers (ENOBUFS)"
+                );
+                None
+            }
+            RecvResult::Err(e) => Some(Err(e.into())),
+        };
+        Some(range.transpose())
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> Stream
+    for BufferRangeStream<'_, BUFFER_SIZE, RING_SIZE>
+{
+    type Item = io::Result<BufferRange<BUFFER_SIZE>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.eof {
+                return Poll::Ready(None);
+            }
+            if !this.armed
+                && let Err(e) = this.arm()
+            {
+                return Poll::Ready(Some(Err(e)));
+            }
+            match this.next_completion() {
+                Some(Ok(None)) => continue,
+                Some(item) => return Poll::Ready(item.transpose()),
+                None => {}
+            }
+            let mut guard = ready!(this.ring.poll_read_ready(cx))?;
+            guard.clear_ready();
+        }
+    }
+}
+
+/// BufferRangeStream yielding the received bytes as Vecs, see `BufferRangeStream::owned`
+pub struct OwnedBufferStream<'a, const BUFFER_SIZE: u32, const RING_SIZE: u16> {
+    inner: BufferRangeStream<'a, BUFFER_SIZE, RING_SIZE>,
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> Stream
+    for OwnedBufferStream<'_, BUFFER_SIZE, RING_SIZE>
+{
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let item = ready!(Pin::new(&mut this.inner).poll_next(cx));
+        Poll::Ready(item.map(|range| {
+            range.map(|range| {
+                let data = Vec::from(&range);
+                this.inner.buffers.recycle_buffers_range(&range);
+                data
+            })
+        }))
+    }
+}
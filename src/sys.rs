@@ -0,0 +1,153 @@
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+/// IORING_REGISTER_PBUF_RING opcode of io_uring_register
+const IORING_REGISTER_PBUF_RING: libc::c_uint = 22;
+
+/// struct io_uring_buf_reg
+#[repr(C)]
+struct BufReg {
+    ring_addr: u64,
+    ring_entries: u32,
+    bgid: u16,
+    flags: u16,
+    resv: [u64; 3],
+}
+
+/// registers a buffer ring with io_uring_register directly on the ring fd,
+/// the same call `Submitter::register_buf_ring_with_flags` makes.
+/// # Safety
+/// `ring_addr` must point to `ring_entries` entries that stay mapped while registered.
+pub(crate) unsafe fn register_buf_ring(
+    ring_fd: BorrowedFd<'_>,
+    ring_addr: u64,
+    ring_entries: u16,
+    bgid: u16,
+    flags: u16,
+) -> std::io::Result<()> {
+    let reg = BufReg {
+        ring_addr,
+        ring_entries: ring_entries as u32,
+        bgid,
+        flags,
+        resv: [0; 3],
+    };
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_io_uring_register,
+            ring_fd.as_raw_fd(),
+            IORING_REGISTER_PBUF_RING,
+            &reg as *const BufReg,
+            1,
+        )
+    };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// IORING_REGISTER_ZCRX_IFQ opcode of io_uring_register (6.15+)
+#[cfg(feature = "zcrx")]
+const IORING_REGISTER_ZCRX_IFQ: libc::c_uint = 32;
+
+/// IORING_MEM_REGION_TYPE_USER: the region is memory mapped by the app
+#[cfg(feature = "zcrx")]
+pub(crate) const IORING_MEM_REGION_TYPE_USER: u32 = 1;
+
+/// struct io_uring_zcrx_rqe, one entry of the refill queue
+#[cfg(feature = "zcrx")]
+#[repr(C)]
+pub(crate) struct ZcrxRqe {
+    pub(crate) off: u64,
+    pub(crate) len: u32,
+    pub(crate) pad: u32,
+}
+
+/// struct io_uring_zcrx_offsets, filled by the kernel: where head, tail and the entries live
+/// in the refill region
+#[cfg(feature = "zcrx")]
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct ZcrxOffsets {
+    pub(crate) head: u32,
+    pub(crate) tail: u32,
+    pub(crate) rqes: u32,
+    pub(crate) resv2: u32,
+    pub(crate) resv: [u64; 2],
+}
+
+/// struct io_uring_zcrx_area_reg
+#[cfg(feature = "zcrx")]
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct ZcrxAreaReg {
+    pub(crate) addr: u64,
+    pub(crate) len: u64,
+    /// filled by the kernel, or-ed into the offsets it reports for this area
+    pub(crate) rq_area_token: u64,
+    pub(crate) flags: u32,
+    pub(crate) dmabuf_fd: u32,
+    pub(crate) resv2: [u64; 2],
+}
+
+/// struct io_uring_region_desc
+#[cfg(feature = "zcrx")]
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct RegionDesc {
+    pub(crate) user_addr: u64,
+    pub(crate) size: u64,
+    pub(crate) flags: u32,
+    pub(crate) id: u32,
+    pub(crate) mmap_offset: u64,
+    pub(crate) resv: [u64; 4],
+}
+
+/// struct io_uring_zcrx_ifq_reg
+#[cfg(feature = "zcrx")]
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct ZcrxIfqReg {
+    pub(crate) if_idx: u32,
+    pub(crate) if_rxq: u32,
+    pub(crate) rq_entries: u32,
+    pub(crate) flags: u32,
+    pub(crate) area_ptr: u64,
+    pub(crate) region_ptr: u64,
+    pub(crate) offsets: ZcrxOffsets,
+    pub(crate) zcrx_id: u32,
+    pub(crate) resv2: u32,
+    pub(crate) resv: [u64; 3],
+}
+
+#[cfg(feature = "zcrx")]
+const _: () = {
+    assert!(size_of::<ZcrxRqe>() == 16);
+    assert!(size_of::<ZcrxAreaReg>() == 48);
+    assert!(size_of::<RegionDesc>() == 64);
+    assert!(size_of::<ZcrxIfqReg>() == 96);
+};
+
+/// registers a zero-copy receive interface queue, the kernel writes the offsets, the
+/// rq_entries it settled on and the zcrx id back into `reg` (and the token into its area).
+/// # Safety
+/// the area and the region `reg` points to must stay mapped while the IoUring lives.
+#[cfg(feature = "zcrx")]
+pub(crate) unsafe fn register_zcrx_ifq(
+    ring_fd: BorrowedFd<'_>,
+    reg: &mut ZcrxIfqReg,
+) -> std::io::Result<()> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_io_uring_register,
+            ring_fd.as_raw_fd(),
+            IORING_REGISTER_ZCRX_IFQ,
+            reg as *mut ZcrxIfqReg,
+            1,
+        )
+    };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
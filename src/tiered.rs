@@ -0,0 +1,109 @@
+use io_uring::IoUring;
+
+use crate::{RingBuffer, buffer_range::BufferRange};
+
+/// a range from either tier of a TieredBufferRing
+#[derive(Debug)]
+pub enum TieredRange<const SMALL_SIZE: u32, const LARGE_SIZE: u32> {
+    Small(BufferRange<SMALL_SIZE>),
+    Large(BufferRange<LARGE_SIZE>),
+}
+
+impl<const SMALL_SIZE: u32, const LARGE_SIZE: u32> TieredRange<SMALL_SIZE, LARGE_SIZE> {
+    /// both segments of the range, whatever its tier
+    pub fn as_parts(&self) -> (&[u8], Option<&[u8]>) {
+        match self {
+            Self::Small(range) => range.as_parts(),
+            Self::Large(range) => range.as_parts(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Small(range) => range.len(),
+            Self::Large(range) => range.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// two buffer groups on the same IoUring, small buffers (e.g. headers) and large ones (e.g. bodies).
+/// submit recvs with either group id, completions are then routed by group id.
+pub struct TieredBufferRing<
+    const SMALL_SIZE: u32,
+    const SMALL_RING: u16,
+    const LARGE_SIZE: u32,
+    const LARGE_RING: u16,
+> {
+    small: RingBuffer<SMALL_SIZE, SMALL_RING>,
+    large: RingBuffer<LARGE_SIZE, LARGE_RING>,
+}
+
+impl<const SMALL_SIZE: u32, const SMALL_RING: u16, const LARGE_SIZE: u32, const LARGE_RING: u16>
+    TieredBufferRing<SMALL_SIZE, SMALL_RING, LARGE_SIZE, LARGE_RING>
+{
+    pub fn new(
+        ring: &IoUring,
+        flags: u16,
+        small_group_id: u16,
+        large_group_id: u16,
+    ) -> std::io::Result<Self> {
+        if small_group_id == large_group_id {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "both tiers need their own group id",
+            ));
+        }
+        Ok(Self {
+            small: RingBuffer::new(ring, flags, small_group_id)?,
+            large: RingBuffer::new(ring, flags, large_group_id)?,
+        })
+    }
+
+    pub fn small_group_id(&self) -> u16 {
+        self.small.group_id()
+    }
+
+    pub fn large_group_id(&self) -> u16 {
+        self.large.group_id()
+    }
+
+    pub fn small(&self) -> &RingBuffer<SMALL_SIZE, SMALL_RING> {
+        &self.small
+    }
+
+    pub fn large(&self) -> &RingBuffer<LARGE_SIZE, LARGE_RING> {
+        &self.large
+    }
+
+    /// range of a completion on group `buffer_group_id`, None if the group isn't one of the tiers
+    pub fn get_buffers_range(
+        &self,
+        buffer_group_id: u16,
+        bid: u16,
+        len: usize,
+    ) -> Option<TieredRange<SMALL_SIZE, LARGE_SIZE>> {
+        if buffer_group_id == self.small.group_id() {
+            self.small
+                .get_buffers_range(bid, len)
+                .map(TieredRange::Small)
+        } else if buffer_group_id == self.large.group_id() {
+            self.large
+                .get_buffers_range(bid, len)
+                .map(TieredRange::Large)
+        } else {
+            None
+        }
+    }
+
+    /// recycles a range to the tier it was taken from, use this only once on a range
+    pub fn recycle_buffers_range(&self, range: &TieredRange<SMALL_SIZE, LARGE_SIZE>) {
+        match range {
+            TieredRange::Small(range) => self.small.recycle_buffers_range(range),
+            TieredRange::Large(range) => self.large.recycle_buffers_range(range),
+        }
+    }
+}
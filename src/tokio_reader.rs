@@ -0,0 +1,145 @@
+use std::{
+    io,
+    os::fd::RawFd,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use io_uring::{IoUring, cqueue, opcode, squeue, types::Fd};
+use tokio::io::{AsyncRead, ReadBuf, unix::AsyncFd};
+
+use crate::{
+    RingBuffer,
+    buffer::Buffer,
+    recv_result::{RecvResult, classify_recv},
+};
+
+const RECV_USER_DATA: u64 = 0x7265_6376;
+
+/// AsyncRead over a socket received through a RingBuffer.
+/// owns the IoUring (the ring fd is polled by tokio to wake on the next CQE),
+/// copies each received buffer into the caller's ReadBuf and recycles it once drained.
+pub struct RingBufferReader<'a, const BUFFER_SIZE: u32, const RING_SIZE: u16> {
+    ring: AsyncFd<IoUring>,
+    buffers: &'a RingBuffer<BUFFER_SIZE, RING_SIZE>,
+    recv: squeue::Entry,
+    pending: Option<(Buffer<BUFFER_SIZE>, usize)>,
+    armed: bool,
+    eof: bool,
+}
+
+impl<'a, const BUFFER_SIZE: u32, const RING_SIZE: u16>
+    RingBufferReader<'a, BUFFER_SIZE, RING_SIZE>
+{
+    /// `ring` must be the one `buffers` was registered on and must not be used for anything else.
+    pub fn new(
+        ring: IoUring,
+        buffers: &'a RingBuffer<BUFFER_SIZE, RING_SIZE>,
+        fd: RawFd,
+    ) -> io::Result<Self> {
+        let recv = opcode::RecvMulti::new(Fd(fd), buffers.group_id())
+            .build()
+            .user_data(RECV_USER_DATA);
+        Ok(Self {
+            ring: AsyncFd::new(ring)?,
+            buffers,
+            recv,
+            pending: None,
+            armed: false,
+            eof: false,
+        })
+    }
+
+    /// gives the ring back, the buffer being drained (if any) is recycled
+    pub fn into_inner(mut self) -> IoUring {
+        if let Some((buffer, _)) = self.pending.take() {
+            self.buffers.recycle_buffer(&buffer);
+        }
+        self.ring.into_inner()
+    }
+
+    /// pushes the recv, submitting what fills the SQ first if it's full.
+    /// returns false if the kernel left no room for it (SQPOLL lagging behind)
+    fn arm(&mut self) -> io::Result<bool> {
+        let ring = self.ring.get_mut();
+        if unsafe { ring.submission().push(&self.recv) }.is_err() {
+            ring.submit()?;
+            if unsafe { ring.submission().push(&self.recv) }.is_err() {
+                return Ok(false);
+            }
+        }
+        ring.submit()?;
+        self.armed = true;
+        Ok(true)
+    }
+
+    /// handles the next CQE of the recv if there is one, returns false when the CQ is empty
+    fn next_completion(&mut self) -> io::Result<bool> {
+        let Some(cqe) = self.ring.get_mut().completion().next() else {
+            return Ok(false);
+        };
+        if cqe.user_data() != RECV_USER_DATA {
+            return Ok(true);
+        }
+        if !cqueue::more(cqe.flags()) {
+            self.armed = false;
+        }
+        match classify_recv(cqe.result(), cqe.flags()) {
+            RecvResult::Data { bid, len, .. } => {
+                let buffer = self
+                    .buffers
+                    .get_buffer(bid, len)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+                self.pending = Some((buffer, 0));
+            }
+            RecvResult::Closed => self.eof = true,
+            RecvResult::NoBuffers => {
+                trace_event!(
+                    warn,
+                    group_id = self.buffers.group_id(),
+                    "recv starved of buffers (ENOBUFS)"
+                );
+            }
+            RecvResult::Err(e) => return Err(e.into()),
+        }
+        Ok(true)
+    }
+}
+
+impl<const BUFFER_SIZE: u32, const RING_SIZE: u16> AsyncRead
+    for RingBufferReader<'_, BUFFER_SIZE, RING_SIZE>
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some((buffer, pos)) = &mut this.pending {
+                let data = &buffer.as_ref()[*pos..];
+                let n = data.len().min(buf.remaining());
+                buf.put_slice(&data[..n]);
+                *pos += n;
+                if *pos == buffer.len {
+                    this.buffers.recycle_buffer(buffer);
+                    this.pending = None;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            if this.eof {
+                return Poll::Ready(Ok(()));
+            }
+            if !this.armed && !this.arm()? {
+                // nothing in flight would wake us, poll again once the SQ has drained
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if this.next_completion()? {
+                continue;
+            }
+            let mut guard = ready!(this.ring.poll_read_ready(cx))?;
+            guard.clear_ready();
+        }
+    }
+}
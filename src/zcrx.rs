@@ -0,0 +1,262 @@
+use std::{
+    cell::Cell,
+    marker::PhantomData,
+    os::fd::{AsRawFd, BorrowedFd},
+    ptr::{NonNull, null_mut},
+    slice,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use io_uring::{IoUring, Probe, opcode};
+use rustix::mm::{MapFlags, ProtFlags, mmap_anonymous, munmap};
+
+use crate::{
+    error::RingBufferError,
+    sys::{self, IORING_MEM_REGION_TYPE_USER, RegionDesc, ZcrxAreaReg, ZcrxIfqReg, ZcrxRqe},
+};
+
+/// bits of a reported offset above this one carry the area token, the low ones the offset
+/// in the area (IORING_ZCRX_AREA_SHIFT)
+const AREA_SHIFT: u32 = 48;
+
+/// zero-copy receive (IORING_OP_RECV_ZC, 6.15+): the NIC writes into an area registered for one
+/// of its hardware rx queues and each completion reports an offset in that area. buffers go back
+/// through a refill queue (the rqe ring) instead of a provided buffer ring, entry by entry.
+///
+/// the IoUring must be set up with DEFER_TASKRUN and CQE32, the queue needs CAP_NET_ADMIN and a
+/// NIC with header split. on a kernel or a NIC without zcrx `new` fails with
+/// ErrorKind::Unsupported, fall back to a RingBuffer with a multishot recv. a ring missing the
+/// setup flags fails with the kernel error.
+/// build the RECV_ZC SQE with `id` as its zcrx_ifq_idx, each CQE32 carries the offset in its
+/// extra 16 bytes (struct io_uring_zcrx_cqe) and the length in its result.
+pub struct ZcRingBuffer {
+    area: NonNull<u8>,
+    area_len: usize,
+    region: NonNull<u8>,
+    region_len: usize,
+    head: NonNull<AtomicU32>,
+    tail: NonNull<AtomicU32>,
+    rqes: NonNull<ZcrxRqe>,
+    rq_entries: u32,
+    /// tail as last published, only the app writes it
+    rq_tail: Cell<u32>,
+    area_token: u64,
+    id: u32,
+    _not_send_sync: PhantomData<*const ()>,
+}
+
+/// `len` bytes received zero-copy at `off` in the area, give it back with `ZcRingBuffer::refill`.
+/// not automatically returned on Drop.
+#[derive(Debug)]
+pub struct ZcBuffer {
+    ptr: NonNull<u8>,
+    len: usize,
+    /// offset as reported by the kernel, area token included
+    off: u64,
+    _not_send_sync: PhantomData<*const ()>,
+}
+
+impl ZcBuffer {
+    pub fn offset(&self) -> u64 {
+        self.off
+    }
+}
+
+impl AsRef<[u8]> for ZcBuffer {
+    fn as_ref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl ZcRingBuffer {
+    /// true if the kernel knows IORING_OP_RECV_ZC, probed on `ring`. the NIC and the ring setup
+    /// flags can still make `new` fail.
+    pub fn is_supported(ring: &IoUring) -> bool {
+        let mut probe = Probe::new();
+        ring.submitter().register_probe(&mut probe).is_ok()
+            && probe.is_supported(opcode::RecvZc::CODE)
+    }
+
+    /// maps an `area_len` bytes receive area and a refill queue of `rq_entries` entries (a power
+    /// of two) and registers them for rx queue `if_rxq` of the interface `if_idx`.
+    pub fn new(
+        ring: &IoUring,
+        if_idx: u32,
+        if_rxq: u32,
+        area_len: usize,
+        rq_entries: u32,
+    ) -> std::io::Result<Self> {
+        if !rq_entries.is_power_of_two() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{rq_entries} refill entries is not a power of two"),
+            ));
+        }
+        let page_size = rustix::param::page_size();
+        let area_len = area_len.next_multiple_of(page_size);
+        // the kernel puts head and tail in the first page, the entries after it
+        let region_len =
+            (page_size + rq_entries as usize * size_of::<ZcrxRqe>()).next_multiple_of(page_size);
+
+        let area = map(area_len).map_err(|source| RingBufferError::PoolMmap {
+            bytes: area_len,
+            source,
+        })?;
+        let region = match map(region_len) {
+            Ok(region) => region,
+            Err(source) => {
+                unsafe { unmap(area, area_len) };
+                return Err(RingBufferError::RingMmap {
+                    bytes: region_len,
+                    source,
+                }
+                .into());
+            }
+        };
+
+        let mut area_reg = ZcrxAreaReg {
+            addr: area.as_ptr() as u64,
+            len: area_len as u64,
+            ..Default::default()
+        };
+        let region_desc = RegionDesc {
+            user_addr: region.as_ptr() as u64,
+            size: region_len as u64,
+            flags: IORING_MEM_REGION_TYPE_USER,
+            ..Default::default()
+        };
+        let mut reg = ZcrxIfqReg {
+            if_idx,
+            if_rxq,
+            rq_entries,
+            area_ptr: &raw mut area_reg as u64,
+            region_ptr: &raw const region_desc as u64,
+            ..Default::default()
+        };
+        let ring_fd = unsafe { BorrowedFd::borrow_raw(ring.as_raw_fd()) };
+        if let Err(e) = unsafe { sys::register_zcrx_ifq(ring_fd, &mut reg) } {
+            unsafe {
+                unmap(area, area_len);
+                unmap(region, region_len);
+            }
+            return Err(unsupported(ring, e));
+        }
+
+        let at = |offset: u32| unsafe { region.add(offset as usize) };
+        trace_event!(
+            debug,
+            zcrx_id = reg.zcrx_id,
+            if_idx,
+            if_rxq,
+            rq_entries = reg.rq_entries,
+            "zero-copy rx queue registered"
+        );
+        Ok(Self {
+            area,
+            area_len,
+            region,
+            region_len,
+            head: at(reg.offsets.head).cast(),
+            tail: at(reg.offsets.tail).cast(),
+            rqes: at(reg.offsets.rqes).cast(),
+            rq_entries: reg.rq_entries,
+            rq_tail: Cell::new(0),
+            area_token: area_reg.rq_area_token,
+            id: reg.zcrx_id,
+            _not_send_sync: PhantomData,
+        })
+    }
+
+    /// zcrx_ifq_idx to put in the RECV_ZC SQEs
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn area_len(&self) -> usize {
+        self.area_len
+    }
+
+    /// the data a RECV_ZC completion reported: `off` from its io_uring_zcrx_cqe, `len` its result.
+    /// None if the offset isn't in this area.
+    pub fn get_buffer(&self, off: u64, len: usize) -> Option<ZcBuffer> {
+        if off & !((1 << AREA_SHIFT) - 1) != self.area_token {
+            return None;
+        }
+        let start = (off & ((1 << AREA_SHIFT) - 1)) as usize;
+        if start.checked_add(len)? > self.area_len {
+            return None;
+        }
+        Some(ZcBuffer {
+            ptr: unsafe { self.area.add(start) },
+            len,
+            off,
+            _not_send_sync: PhantomData,
+        })
+    }
+
+    /// hands the buffer back to the NIC through the refill queue, use this only once per buffer.
+    /// false if the queue is full (the kernel hasn't consumed the previous entries yet), retry later.
+    pub fn refill(&self, buffer: &ZcBuffer) -> bool {
+        let tail = self.rq_tail.get();
+        let head = unsafe { self.head.as_ref() }.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == self.rq_entries {
+            return false;
+        }
+        unsafe {
+            let rqe = self
+                .rqes
+                .as_ptr()
+                .add((tail & (self.rq_entries - 1)) as usize);
+            (*rqe).off = buffer.off;
+            (*rqe).len = buffer.len as u32;
+            (*rqe).pad = 0;
+            // pairs with the kernel's acquire of the tail, like the provided ring tail
+            self.tail
+                .as_ref()
+                .store(tail.wrapping_add(1), Ordering::Release);
+        }
+        self.rq_tail.set(tail.wrapping_add(1));
+        true
+    }
+}
+
+impl Drop for ZcRingBuffer {
+    /// the kernel keeps the pages pinned until the IoUring is closed, unmapping them here only
+    /// drops the app's view
+    fn drop(&mut self) {
+        unsafe {
+            unmap(self.area, self.area_len);
+            unmap(self.region, self.region_len);
+        }
+    }
+}
+
+fn map(len: usize) -> std::io::Result<NonNull<u8>> {
+    let ptr = unsafe {
+        mmap_anonymous(
+            null_mut(),
+            len,
+            ProtFlags::READ | ProtFlags::WRITE,
+            MapFlags::PRIVATE | MapFlags::POPULATE,
+        )
+    }?;
+    Ok(unsafe { NonNull::new_unchecked(ptr.cast()) })
+}
+
+unsafe fn unmap(ptr: NonNull<u8>, len: usize) {
+    let _ = unsafe { munmap(ptr.as_ptr().cast(), len) };
+}
+
+/// reports as Unsupported the errors meaning the caller should fall back: ENODEV from a NIC
+/// without support, and EINVAL/EOPNOTSUPP from a kernel without zcrx. on a kernel with it those
+/// are real errors (e.g. a ring set up without CQE32/DEFER_TASKRUN) and are passed through.
+fn unsupported(ring: &IoUring, e: std::io::Error) -> std::io::Error {
+    use rustix::io::Errno;
+    let is = |errno: Errno| e.raw_os_error() == Some(errno.raw_os_error());
+    let fallback = is(Errno::NODEV)
+        || ((is(Errno::INVAL) || is(Errno::OPNOTSUPP)) && !ZcRingBuffer::is_supported(ring));
+    match fallback {
+        true => std::io::Error::new(std::io::ErrorKind::Unsupported, e),
+        false => e,
+    }
+}
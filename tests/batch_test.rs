@@ -0,0 +1,51 @@
+use std::os::{fd::AsRawFd, unix::net::UnixDatagram};
+
+use io_uring::types::Fd;
+use io_uring_rb::{RingBuffer, recv_result::RecvResult};
+
+#[test]
+fn test_recv_batch_selects_one_buffer_each() {
+    let mut ring = io_uring::IoUring::new(16).unwrap();
+    let br = RingBuffer::<64, 8>::new(&ring, 0, 0).unwrap();
+    let (tx, rx) = UnixDatagram::pair().unwrap();
+    for i in 0..4u8 {
+        tx.send(&[i; 10]).unwrap();
+    }
+
+    let user_data = br.push_recv_batch(&mut ring.submission(), Fd(rx.as_raw_fd()), 4, 100);
+    assert_eq!(user_data, 100..104);
+    ring.submit_and_wait(4).unwrap();
+
+    let mut received = Vec::new();
+    for (_, result, buffer) in br.collect_recv_batch(ring.completion(), user_data) {
+        assert!(matches!(result, RecvResult::Data { len: 10, .. }));
+        let buffer = buffer.unwrap();
+        received.push(buffer.as_ref()[0]);
+        br.recycle_buffer(&buffer);
+    }
+    received.sort();
+    assert_eq!(received, [0, 1, 2, 3]);
+}
+
+#[test]
+fn test_poll_then_recv_linked_pair() {
+    let mut ring = io_uring::IoUring::new(16).unwrap();
+    let br = RingBuffer::<64, 8>::new(&ring, 0, 0).unwrap();
+    let (tx, rx) = UnixDatagram::pair().unwrap();
+
+    for entry in br.poll_then_recv(Fd(rx.as_raw_fd()), 1, 2) {
+        unsafe { ring.submission().push(&entry).unwrap() };
+    }
+    ring.submit().unwrap();
+    tx.send(b"ready").unwrap();
+    ring.submit_and_wait(2).unwrap();
+
+    let cqes: Vec<_> = ring.completion().collect();
+    assert!(
+        cqes.iter()
+            .any(|cqe| cqe.user_data() == 1 && cqe.result() > 0)
+    );
+    let (_, result, buffer) = br.collect_recv_batch(cqes, 2..3).next().unwrap();
+    assert!(matches!(result, RecvResult::Data { len: 5, .. }));
+    assert_eq!(buffer.unwrap(), &b"ready"[..]);
+}
@@ -0,0 +1,39 @@
+use std::{net::UdpSocket, os::fd::AsRawFd};
+
+use io_uring::types::Fd;
+use io_uring_rb::RingBuffer;
+
+#[test]
+fn test_forward_zc_recycles_after_notification() {
+    let mut ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 4>::new(&ring, 0, 0).unwrap();
+    let (tx, rx) = (
+        UdpSocket::bind("127.0.0.1:0").unwrap(),
+        UdpSocket::bind("127.0.0.1:0").unwrap(),
+    );
+    tx.connect(rx.local_addr().unwrap()).unwrap();
+
+    // a wrapped range of 20 bytes, as if received
+    let data: Vec<u8> = (0..20).collect();
+    let pool = unsafe { std::slice::from_raw_parts_mut(br.pool_ptr().as_ptr(), br.pool_len()) };
+    pool[48..64].copy_from_slice(&data[..16]);
+    pool[..4].copy_from_slice(&data[16..]);
+    let range = br.get_buffers_range(3, 20).unwrap();
+    assert_eq!(br.in_flight(), 2);
+
+    let mut pending = br.forward_zc(Fd(tx.as_raw_fd()), range);
+    unsafe { ring.submission().push(&pending.entry()).unwrap() };
+    let sent = loop {
+        ring.submit_and_wait(1).unwrap();
+        let cqes: Vec<_> = ring.completion().collect();
+        if let Some(sent) = cqes.iter().find_map(|cqe| pending.complete(cqe)) {
+            break sent;
+        }
+    };
+    assert_eq!(sent.unwrap(), 20);
+    assert_eq!(br.in_flight(), 0);
+
+    let mut out = [0; 32];
+    assert_eq!(rx.recv(&mut out).unwrap(), 20);
+    assert_eq!(&out[..20], &data[..]);
+}
@@ -0,0 +1,65 @@
+use io_uring_rb::RingBuffer;
+
+fn fill<const B: u32, const R: u16>(br: &RingBuffer<B, R>, data: impl IntoIterator<Item = u8>) {
+    let pool = unsafe { std::slice::from_raw_parts_mut(br.pool_ptr().as_ptr(), br.pool_len()) };
+    for (dst, b) in pool.iter_mut().zip(data) {
+        *dst = b;
+    }
+}
+
+#[test]
+fn test_hexdump_lines() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<4096, 2>::new(&ring, 0, 0).unwrap();
+    fill(&br, *b"GET / HTTP/1.1\r\nHost: a\0\x7f");
+
+    let buffer = br.get_buffer(0, 25).unwrap();
+    let dump = buffer.hexdump().to_string();
+    let lines: Vec<_> = dump.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "00000000  47 45 54 20 2f 20 48 54 54 50 2f 31 2e 31 0d 0a  |GET / HTTP/1.1..|",
+            // the last line is partial, its hex column padded so the ascii one stays aligned
+            "00000010  48 6f 73 74 3a 20 61 00 7f                       |Host: a..|",
+        ]
+    );
+    br.recycle_buffer(&buffer);
+}
+
+#[test]
+fn test_hexdump_offsets_run_across_segments() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 2>::new(&ring, 0, 0).unwrap();
+    fill(&br, 0..128);
+
+    // all of buffer 1 then 32 bytes wrapped into buffer 0
+    let range = br.get_buffers_range(1, 64 + 32).unwrap();
+    let dump = range.hexdump().to_string();
+    let lines: Vec<_> = dump.lines().collect();
+    assert_eq!(lines.len(), 6);
+    // the offset keeps counting from the start of the range past the wrap
+    assert!(lines[3].starts_with("00000030  70 71"));
+    assert!(lines[4].starts_with("00000040  00 01"));
+    assert!(lines[5].starts_with("00000050  10 11"));
+    br.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_hexdump_is_capped() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<4096, 2>::new(&ring, 0, 0).unwrap();
+
+    let range = br.get_buffers_range(0, 5000).unwrap();
+    let dump = range.hexdump().to_string();
+    let lines: Vec<_> = dump.lines().collect();
+    assert_eq!(lines.len(), 4096 / 16 + 1);
+    assert!(lines[4096 / 16 - 1].starts_with("00000ff0 "));
+    assert_eq!(lines.last().unwrap(), &"... (904 more bytes)");
+    br.recycle_buffers_range(&range);
+
+    // exactly the cap shows no ellipsis
+    let range = br.get_buffers_range(0, 4096).unwrap();
+    assert!(!range.hexdump().to_string().contains("more bytes"));
+    br.recycle_buffers_range(&range);
+}
@@ -0,0 +1,58 @@
+//! models the app-side publication of ring entries under loom:
+//! RUSTFLAGS="--cfg loom" cargo test --release --test loom_tail
+#![cfg(loom)]
+
+use loom::cell::UnsafeCell;
+use loom::sync::Arc;
+use loom::sync::atomic::{AtomicU16, Ordering};
+use loom::thread;
+
+const RING_SIZE: u16 = 2;
+
+/// same layout logic as the buffer ring: entries written in place, then the tail published
+struct ModelRing {
+    entries: [UnsafeCell<(u64, u16)>; RING_SIZE as usize],
+    tail: AtomicU16,
+}
+
+impl ModelRing {
+    fn new() -> Self {
+        Self {
+            entries: [UnsafeCell::new((0, 0)), UnsafeCell::new((0, 0))],
+            tail: AtomicU16::new(0),
+        }
+    }
+
+    /// mirrors setup_ring_entry + set_tail in recycle
+    fn recycle(&self, addr: u64, bid: u16) {
+        let tail = self.tail.load(Ordering::Acquire);
+        let idx = (tail & (RING_SIZE - 1)) as usize;
+        self.entries[idx].with_mut(|e| unsafe { *e = (addr, bid) });
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+    }
+}
+
+#[test]
+fn entries_visible_before_tail() {
+    loom::model(|| {
+        let ring = Arc::new(ModelRing::new());
+
+        let app = {
+            let ring = ring.clone();
+            thread::spawn(move || {
+                ring.recycle(0x1000, 0);
+                ring.recycle(0x2000, 1);
+            })
+        };
+
+        // the "kernel" consumes every entry it sees published
+        let tail = ring.tail.load(Ordering::Acquire);
+        for head in 0..tail {
+            let (addr, bid) = ring.entries[head as usize].with(|e| unsafe { *e });
+            assert_eq!(bid, head);
+            assert_eq!(addr, 0x1000 * (head as u64 + 1));
+        }
+
+        app.join().unwrap();
+    });
+}
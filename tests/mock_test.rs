@@ -0,0 +1,279 @@
+use io_uring_rb::buffer_source::BufferSource;
+use io_uring_rb::mock::MockRing;
+
+/// consumer logic written against BufferSource, as an app would
+fn collect<S: BufferSource<16>>(source: &S, bid: u16, len: usize, out: &mut Vec<u8>) {
+    let range = source.get_buffers_range(bid, len).unwrap();
+    out.extend(Vec::from(&range));
+    source.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_mock_injected_completions() {
+    let mock = MockRing::<16, 4>::new();
+    let mut received = Vec::new();
+
+    let data: Vec<u8> = (0..40).collect();
+    let (bid, len) = mock.inject(&data);
+    assert_eq!((bid, len), (0, 40));
+    collect(&mock, bid, len, &mut received);
+
+    // 3 buffers used, the next completion wraps the ring
+    let (bid, len) = mock.inject(&data[..20]);
+    assert_eq!(bid, 3);
+    collect(&mock, bid, len, &mut received);
+
+    assert_eq!(received, [&data[..], &data[..20]].concat());
+    assert_eq!(mock.recycled_bids(), [0, 1, 2, 3, 0]);
+}
+
+#[test]
+fn test_write_all_to_wrapped_range() {
+    let mock = MockRing::<16, 4>::new();
+    let data: Vec<u8> = (0..60).collect();
+    mock.inject(&data[..40]);
+    let (bid, len) = mock.inject(&data[40..]);
+
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    assert!(range.as_parts().1.is_some());
+    assert!(range.is_wrapped());
+    assert_eq!(range.first_bid(), bid);
+    assert_eq!((&range).into_iter().count(), range.segment_count());
+    assert_eq!((&range).into_iter().flatten().count(), len);
+    let mut out = Vec::new();
+    range.write_all_to(&mut out).unwrap();
+    assert_eq!(out, &data[40..]);
+}
+
+#[test]
+fn test_fold_bytes_across_wrap() {
+    let mock = MockRing::<16, 4>::new();
+    mock.inject(&[0; 40]);
+    let (bid, len) = mock.inject(b"Wikipedia is wrapping the ring");
+
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    assert!(range.as_parts().1.is_some());
+    let sum = range.fold_bytes(0u32, |acc, bytes| {
+        acc + bytes.iter().map(|&b| b as u32).sum::<u32>()
+    });
+    assert_eq!(
+        sum,
+        b"Wikipedia is wrapping the ring"
+            .iter()
+            .map(|&b| b as u32)
+            .sum()
+    );
+    #[cfg(feature = "checksum")]
+    {
+        let (bid, len) = mock.inject(b"Wikipedia");
+        assert_eq!(
+            mock.get_buffers_range(bid, len).unwrap().adler32(),
+            0x11E6_0398
+        );
+    }
+}
+
+#[test]
+fn test_parse_recvmsg_out() {
+    use io_uring_rb::recvmsg::{parse_recvmsg, recvmsg_header};
+
+    let mock = MockRing::<128, 2>::new();
+    let msghdr = recvmsg_header(0);
+    let payload = b"hello";
+
+    // io_uring_recvmsg_out: namelen, controllen, payloadlen, flags
+    let mut data = Vec::new();
+    for field in [16u32, 0, payload.len() as u32, 0] {
+        data.extend(field.to_ne_bytes());
+    }
+    // sockaddr_in 127.0.0.1:4242 in a sockaddr_in6 sized name area
+    let mut name = [0u8; 28];
+    name[..2].copy_from_slice(&2u16.to_ne_bytes());
+    name[2..4].copy_from_slice(&4242u16.to_be_bytes());
+    name[4..8].copy_from_slice(&[127, 0, 0, 1]);
+    data.extend(name);
+    data.extend(payload);
+
+    let (bid, len) = mock.inject(&data);
+    let buffer = mock.get_buffer(bid, len).unwrap();
+    let msg = parse_recvmsg(&buffer, &msghdr).unwrap();
+    assert_eq!(msg.addr, Some("127.0.0.1:4242".parse().unwrap()));
+    assert_eq!(msg.payload, payload);
+    assert!(msg.control.is_empty());
+    assert!(!msg.truncated);
+}
+
+#[test]
+fn test_truncate_keeps_recycled_buffers() {
+    let mock = MockRing::<16, 4>::new();
+    let data: Vec<u8> = (0..60).collect();
+    mock.inject(&data[..40]);
+    let (bid, len) = mock.inject(&data[40..]);
+
+    let mut range = mock.get_buffers_range(bid, len).unwrap();
+    range.truncate(18);
+    assert_eq!(range.as_parts(), (&data[40..56], Some(&data[56..58])));
+    assert_eq!(range.as_contiguous(), None);
+    range.truncate(10);
+    assert_eq!(range.as_parts(), (&data[40..50], None));
+    assert!(!range.is_wrapped());
+    assert_eq!(range.as_contiguous(), Some(&data[40..50]));
+    assert_eq!(range.len(), 10);
+    range.truncate(15);
+    assert_eq!(range.len(), 10);
+
+    mock.recycle_buffers_range(&range);
+    assert_eq!(mock.recycled_bids(), [3, 0]);
+}
+
+#[test]
+fn test_range_eq_slice_across_wrap() {
+    let mock = MockRing::<16, 4>::new();
+    let data: Vec<u8> = (0..60).collect();
+    mock.inject(&data[..40]);
+    let (bid, len) = mock.inject(&data[40..]);
+
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    assert_eq!(range, &data[40..]);
+    assert_eq!(
+        (range[0], range[15], range[16], range[19]),
+        (40, 55, 56, 59)
+    );
+    assert!(range != data[40..59]);
+    assert!(range != [&data[40..50], &[0; 10][..]].concat()[..]);
+
+    let (bid, len) = mock.inject(b"ping");
+    let buffer = mock.get_buffer(bid, len).unwrap();
+    assert_eq!(buffer, &b"ping"[..]);
+}
+
+#[test]
+fn test_buffer_split_at() {
+    let mock = MockRing::<16, 2>::new();
+    let (bid, len) = mock.inject(b"headbody");
+    let buffer = mock.get_buffer(bid, len).unwrap();
+
+    assert_eq!(buffer.split_at(4), Some((&b"head"[..], &b"body"[..])));
+    assert_eq!(buffer.split_at(8), Some((&b"headbody"[..], &b""[..])));
+    assert_eq!(buffer.split_at(9), None);
+}
+
+#[test]
+#[should_panic]
+fn test_range_index_past_the_end() {
+    let mock = MockRing::<16, 4>::new();
+    let (bid, len) = mock.inject(&[1; 20]);
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    let _ = range[20];
+}
+
+#[test]
+fn test_fill_iovecs_for_wrapped_range() {
+    let mock = MockRing::<16, 4>::new();
+    mock.inject(&[0; 40]);
+    let (bid, len) = mock.inject(&[7; 20]);
+    let range = mock.get_buffers_range(bid, len).unwrap();
+
+    let mut iov = [libc::iovec {
+        iov_base: std::ptr::null_mut(),
+        iov_len: 0,
+    }; 2];
+    assert_eq!(range.fill_iovecs(&mut iov), 2);
+    let (first, second) = range.as_parts();
+    assert_eq!(
+        (iov[0].iov_base as *const u8, iov[0].iov_len),
+        (first.as_ptr(), 16)
+    );
+    assert_eq!(
+        (iov[1].iov_base as *const u8, iov[1].iov_len),
+        (second.unwrap().as_ptr(), 4)
+    );
+}
+
+#[test]
+fn test_split_on_across_wrap() {
+    use std::borrow::Cow;
+
+    let mock = MockRing::<16, 4>::new();
+    mock.inject(&[0; 40]);
+    // 16 bytes in buffer 3, the rest wraps to buffer 0, "straddle" crosses the boundary
+    let (bid, len) = mock.inject(b"one\ntwo\nstraddling\nlast");
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    assert!(range.is_wrapped());
+
+    let frames: Vec<_> = range.split_on(b'\n').collect();
+    assert_eq!(frames, [&b"one"[..], b"two", b"straddling", b"last"]);
+    assert!(matches!(frames[2], Cow::Owned(_)));
+    assert!(matches!(frames[3], Cow::Borrowed(_)));
+
+    let (bid, len) = mock.inject(b"a\nb\n");
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    let frames: Vec<_> = range.split_on(b'\n').collect();
+    assert_eq!(frames, [&b"a"[..], b"b", b""]);
+}
+
+#[test]
+fn test_pwrite_to_file_offset() {
+    use std::os::unix::fs::FileExt;
+
+    let mock = MockRing::<16, 4>::new();
+    mock.inject(&[0; 40]);
+    let data: Vec<u8> = (0..30).collect();
+    let (bid, len) = mock.inject(&data);
+    let range = mock.get_buffers_range(bid, len).unwrap();
+    assert!(range.is_wrapped());
+
+    let path = std::env::temp_dir().join(format!("io_uring_rb_pwrite_{}", std::process::id()));
+    let file = std::fs::File::options()
+        .create(true)
+        .truncate(true)
+        .read(true)
+        .write(true)
+        .open(&path)
+        .unwrap();
+    assert_eq!(range.pwrite_to(&file, 5).unwrap(), 30);
+    mock.recycle_buffers_range(&range);
+
+    let mut out = [0xff; 35];
+    file.read_exact_at(&mut out, 0).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(out[..5], [0; 5]);
+    assert_eq!(out[5..], data[..]);
+}
+
+#[test]
+fn test_slice_across_wrap() {
+    let mock = MockRing::<16, 4>::new();
+    mock.inject(&[0; 40]);
+    let data: Vec<u8> = (0..30).collect();
+    let (bid, len) = mock.inject(&data);
+    let range = mock.get_buffers_range(bid, len).unwrap();
+
+    // 16 bytes in buffer 3, the rest wraps to buffer 0
+    let head = range.slice(0..4).unwrap();
+    assert!(matches!(head, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(*head, data[..4]);
+    let tail = range.slice(20..30).unwrap();
+    assert!(matches!(tail, std::borrow::Cow::Borrowed(_)));
+    assert_eq!(*tail, data[20..]);
+    let straddling = range.slice(4..20).unwrap();
+    assert!(matches!(straddling, std::borrow::Cow::Owned(_)));
+    assert_eq!(*straddling, data[4..20]);
+    assert_eq!(*range.slice(30..30).unwrap(), []);
+
+    assert!(range.slice(4..31).is_none());
+    let (start, end) = (5, 4);
+    assert!(range.slice(start..end).is_none());
+    mock.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_buffer_subslice() {
+    let mock = MockRing::<16, 4>::new();
+    let (bid, len) = mock.inject(b"HDR:payload");
+    let buffer = mock.get_buffer(bid, len).unwrap();
+    assert_eq!(buffer.subslice(4..len), Some(&b"payload"[..]));
+    assert_eq!(buffer.subslice(0..0), Some(&b""[..]));
+    assert_eq!(buffer.subslice(4..len + 1), None);
+    mock.recycle_buffer(&buffer);
+}
@@ -0,0 +1,34 @@
+use io_uring_rb::multishot::MultishotRegistry;
+
+const F_BUFFER: u32 = 1 << 0;
+const F_MORE: u32 = 1 << 1;
+
+#[test]
+fn test_registry_tracks_each_socket() {
+    let mut registry = MultishotRegistry::new();
+    registry.arm(1);
+    registry.arm(2);
+    registry.arm(3);
+    assert_eq!(registry.armed_count(), 3);
+
+    // still armed while F_MORE is set
+    assert!(!registry.on_completion(1, 100, F_BUFFER | F_MORE | (4 << 16)));
+    assert!(registry.is_armed(1));
+
+    // the last data completion and ENOBUFS both end the multishot
+    assert!(registry.on_completion(1, 100, F_BUFFER | (5 << 16)));
+    assert!(registry.on_completion(2, -105, 0));
+    // closed and failed sockets are not re-armed
+    assert!(!registry.on_completion(3, 0, 0));
+    assert_eq!(registry.armed_count(), 0);
+
+    let mut disarmed: Vec<_> = registry.disarmed().collect();
+    disarmed.sort();
+    assert_eq!(disarmed, [1, 2, 3]);
+
+    registry.arm(1);
+    registry.forget(3);
+    assert!(registry.is_armed(1));
+    assert!(!registry.on_completion(3, 100, F_BUFFER));
+    assert_eq!(registry.disarmed().collect::<Vec<_>>(), [2]);
+}
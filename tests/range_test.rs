@@ -0,0 +1,515 @@
+use io_uring_rb::RingBuffer;
+
+const BUFFER_SIZE: u32 = 1024;
+const SIZE: u16 = 16;
+const POOL_BYTES: usize = BUFFER_SIZE as usize * SIZE as usize;
+
+#[test]
+fn test_range_rejects_len_over_pool() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    assert!(br.get_buffers_range(0, POOL_BYTES).is_some());
+    assert!(br.get_buffers_range(3, POOL_BYTES).is_some());
+    assert!(br.get_buffers_range(0, POOL_BYTES + 1).is_none());
+    assert!(br.get_buffers_range(3, usize::MAX).is_none());
+}
+
+#[test]
+fn test_range_wraps_at_ring_end() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    let range = br
+        .get_buffers_range(SIZE - 1, 2 * BUFFER_SIZE as usize + 10)
+        .unwrap();
+    let (first, second) = range.as_parts();
+    assert_eq!(first.len(), BUFFER_SIZE as usize);
+    assert_eq!(second.unwrap().len(), BUFFER_SIZE as usize + 10);
+    assert_eq!(range.len(), 2 * BUFFER_SIZE as usize + 10);
+    assert_eq!(
+        br.buffer_ids_of(&range).collect::<Vec<_>>(),
+        [SIZE - 1, 0, 1]
+    );
+    br.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_unchecked_range_matches_the_checked_one() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    for (bid, len) in [
+        (0, 1),
+        (3, BUFFER_SIZE as usize),
+        (SIZE - 1, 2 * BUFFER_SIZE as usize + 10),
+        (0, POOL_BYTES),
+    ] {
+        let checked = br.get_buffers_range(bid, len).unwrap();
+        let checked_parts = checked.as_parts();
+        let checked_parts = (
+            checked_parts.0.as_ptr_range(),
+            checked_parts.1.map(<[u8]>::as_ptr_range),
+        );
+        let checked_ids: Vec<_> = br.buffer_ids_of(&checked).collect();
+        br.recycle_buffers_range(&checked);
+
+        let unchecked = unsafe { br.get_buffers_range_unchecked(bid, len) };
+        let parts = unchecked.as_parts();
+        assert_eq!(
+            (parts.0.as_ptr_range(), parts.1.map(<[u8]>::as_ptr_range)),
+            checked_parts
+        );
+        assert_eq!(unchecked.len(), len);
+        assert_eq!(
+            br.buffer_ids_of(&unchecked).collect::<Vec<_>>(),
+            checked_ids
+        );
+        // the buffers are issued like with the checked range
+        assert_eq!(br.in_flight() as usize, checked_ids.len());
+        assert!(checked_ids.iter().all(|&bid| br.is_issued(bid)));
+        br.recycle_buffers_range(&unchecked);
+        assert_eq!(br.in_flight(), 0);
+    }
+}
+
+#[test]
+fn test_empty_range() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    for bid in [0, 5] {
+        let range = br.get_buffers_range(bid, 0).unwrap();
+        assert!(range.is_empty());
+        assert_eq!(range.as_parts(), (&[][..], None));
+        assert_eq!(br.buffer_ids_of(&range).count(), 0);
+
+        let buffer = br.get_buffer(bid, 0).unwrap();
+        assert!(buffer.as_ref().is_empty());
+
+        // the kernel kept both buffers, recycling them must not publish their bid again
+        let tail = br.tail();
+        br.recycle_buffers_range(&range);
+        br.recycle_buffer(&buffer);
+        assert_eq!(br.tail(), tail);
+    }
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_range_with_mtu_sized_buffers() {
+    const MTU: u32 = 1500;
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<MTU, SIZE>::new(&ring, 0, 0).unwrap();
+
+    let range = br.get_buffers_range(SIZE - 2, 3 * MTU as usize).unwrap();
+    let (first, second) = range.as_parts();
+    assert_eq!(first.len(), 2 * MTU as usize);
+    assert_eq!(second.unwrap().len(), MTU as usize);
+    assert_eq!(
+        br.buffer_ids_of(&range).collect::<Vec<_>>(),
+        [SIZE - 2, SIZE - 1, 0]
+    );
+    br.recycle_buffers_range(&range);
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_saturating_recycle_ignores_duplicates() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    let buffer = br.get_buffer(2, 10).unwrap();
+    assert_eq!(br.in_flight(), 1);
+    assert!(br.is_issued(2) && !br.is_issued(3) && !br.is_issued(SIZE));
+    assert!(br.recycle_buffer_saturating(&buffer));
+    let tail = br.tail();
+    assert!(!br.recycle_buffer_saturating(&buffer));
+    assert_eq!(br.tail(), tail);
+    assert_eq!(br.in_flight(), 0);
+    assert!(!br.is_issued(2));
+}
+
+#[test]
+fn test_recycle_by_id_range_without_the_range() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    let len = 3 * BUFFER_SIZE as usize;
+    // the range itself isn't kept
+    br.get_buffers_range(SIZE - 1, len).unwrap();
+    assert_eq!(br.in_flight(), 3);
+
+    br.recycle_by_id_range(SIZE - 1, len).unwrap();
+    assert_eq!(br.in_flight(), 0);
+    assert!(br.recycle_by_id_range(SIZE, 1).is_err());
+    assert!(br.recycle_by_id_range(0, POOL_BYTES + 1).is_err());
+}
+
+#[test]
+fn test_try_get_buffers_range_backpressure() {
+    use io_uring_rb::error::RingBufferError;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::builder(0)
+        .max_outstanding_bytes(4 * BUFFER_SIZE as usize)
+        .build(&ring)
+        .unwrap();
+
+    let held = br
+        .try_get_buffers_range(0, 3 * BUFFER_SIZE as usize)
+        .unwrap();
+    let err = br
+        .try_get_buffers_range(3, BUFFER_SIZE as usize + 1)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WouldBlock);
+    assert!(matches!(
+        err.get_ref()
+            .and_then(|e| e.downcast_ref::<RingBufferError>()),
+        Some(RingBufferError::Backpressure { .. })
+    ));
+
+    br.recycle_buffers_range(&held);
+    assert!(
+        br.try_get_buffers_range(3, BUFFER_SIZE as usize + 1)
+            .is_ok()
+    );
+}
+
+#[test]
+fn test_deferred_recycle_flush() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    let range = br.get_buffers_range(0, 2 * BUFFER_SIZE as usize).unwrap();
+    let buffer = br.get_buffer(2, 1).unwrap();
+    let tail = br.tail();
+
+    br.defer_recycle_range(&range);
+    br.defer_recycle(&buffer);
+    assert_eq!(br.tail(), tail);
+    assert_eq!(br.in_flight(), 3);
+
+    assert_eq!(br.flush(), 3);
+    assert_eq!(br.tail(), tail.wrapping_add(3));
+    assert_eq!(br.in_flight(), 0);
+    assert_eq!(br.flush(), 0);
+}
+
+#[test]
+fn test_recycle_buffer_at_offset() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    let buffer = br.get_buffer(4, 100).unwrap();
+    assert!(br.recycle_buffer_at(&buffer, 1000, 100).is_err());
+
+    let tail = br.tail();
+    let payload = BUFFER_SIZE - 100;
+    br.recycle_buffer_at(&buffer, 100, payload).unwrap();
+    let slot = tail as usize % SIZE as usize;
+    let entry = unsafe { &*br.ring_entries_ptr().as_ptr().add(slot) };
+    let buffer_start = unsafe { br.pool_ptr().as_ptr().add(4 * BUFFER_SIZE as usize) };
+    assert_eq!(entry.addr(), buffer_start as u64 + 100);
+    assert_eq!(entry.len(), payload);
+    assert_eq!(entry.bid(), 4);
+}
+
+#[test]
+fn test_recorder_counts() {
+    use io_uring_rb::recorder::BufRingRecorder;
+    use std::{cell::Cell, rc::Rc};
+
+    #[derive(Default)]
+    struct Counts {
+        issued: Cell<u32>,
+        recycled: Cell<u32>,
+        bytes: Cell<usize>,
+    }
+
+    impl BufRingRecorder for Counts {
+        fn on_issue(&self, count: u16) {
+            self.issued.set(self.issued.get() + count as u32);
+        }
+        fn on_recycle(&self, count: u16) {
+            self.recycled.set(self.recycled.get() + count as u32);
+        }
+        fn on_starvation(&self) {}
+        fn on_bytes(&self, len: usize) {
+            self.bytes.set(self.bytes.get() + len);
+        }
+    }
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let counts = Rc::new(Counts::default());
+    let br =
+        RingBuffer::<BUFFER_SIZE, SIZE>::new_with_recorder(&ring, 0, 0, counts.clone()).unwrap();
+
+    let range = br.get_buffers_range(0, BUFFER_SIZE as usize + 1).unwrap();
+    let buffer = br.get_buffer(2, 10).unwrap();
+    br.recycle_buffers_range(&range);
+    br.recycle_buffer(&buffer);
+
+    assert_eq!(counts.issued.get(), 3);
+    assert_eq!(counts.recycled.get(), 3);
+    assert_eq!(counts.bytes.get(), BUFFER_SIZE as usize + 11);
+}
+
+#[test]
+fn test_recycle_all_outstanding() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    br.get_buffers_range(SIZE - 2, 3 * BUFFER_SIZE as usize)
+        .unwrap();
+    let buffer = br.get_buffer(5, 1).unwrap();
+    br.defer_recycle(&buffer);
+    let tail = br.tail();
+
+    assert_eq!(br.recycle_all_outstanding(), 4);
+    assert_eq!(br.in_flight(), 0);
+    assert_eq!(br.tail(), tail.wrapping_add(4));
+    assert_eq!(br.recycle_all_outstanding(), 0);
+}
+
+#[test]
+fn test_take_owned_recycles() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+
+    assert_eq!(br.range_slot_count(SIZE - 1, BUFFER_SIZE as usize + 5), 2);
+    assert_eq!(br.range_slot_count(SIZE, 1), 0);
+    let data = br.take_owned(SIZE - 1, BUFFER_SIZE as usize + 5).unwrap();
+    assert_eq!(data.len(), BUFFER_SIZE as usize + 5);
+    assert_eq!(br.in_flight(), 0);
+    assert!(br.take_owned(SIZE, 1).is_none());
+}
+
+#[test]
+fn test_range_from_segments() {
+    use io_uring_rb::buffer_range::BufferRange;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+    let base = unsafe { br.pool_ptr() };
+    let last = unsafe { base.add((SIZE - 1) as usize * BUFFER_SIZE as usize) };
+
+    let range = unsafe {
+        BufferRange::<BUFFER_SIZE>::from_segments((last, BUFFER_SIZE as usize), Some((base, 10)))
+    };
+    assert!(range.is_wrapped());
+    assert_eq!(range.len(), BUFFER_SIZE as usize + 10);
+    assert_eq!(br.buffer_ids_of(&range).collect::<Vec<_>>(), [SIZE - 1, 0]);
+    let tail = br.tail();
+    br.recycle_buffers_range(&range);
+    assert_eq!(br.tail(), tail.wrapping_add(2));
+}
+
+#[test]
+fn test_recycle_range_into_needs_a_shared_pool() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+    let other = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 1).unwrap();
+
+    let range = br.get_buffers_range(2, 2 * BUFFER_SIZE as usize).unwrap();
+    let other_tail = other.tail();
+    let err = br.recycle_range_into(&other, &range).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!(other.tail(), other_tail);
+    assert_eq!(br.in_flight(), 2);
+    br.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_recycle_range_into_a_ring_sharing_the_pool() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let other_ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+    let shared =
+        unsafe { RingBuffer::<BUFFER_SIZE, SIZE>::new_sharing_pool(&other_ring, 0, 0, &br) }
+            .unwrap();
+    assert_eq!(unsafe { shared.pool_ptr() }, unsafe { br.pool_ptr() });
+    assert_eq!(shared.tail(), 0);
+
+    unsafe {
+        br.pool_ptr()
+            .as_ptr()
+            .add(2 * BUFFER_SIZE as usize)
+            .write(0x2a)
+    };
+    let range = br.get_buffers_range(2, 2 * BUFFER_SIZE as usize).unwrap();
+    br.recycle_range_into(&shared, &range).unwrap();
+    assert_eq!(br.in_flight(), 0);
+    assert_eq!(shared.tail(), 2);
+    for (slot, bid) in [(0, 2u16), (1, 3)] {
+        let entry = unsafe { &*shared.ring_entries_ptr().as_ptr().add(slot) };
+        assert_eq!(entry.bid(), bid);
+        assert_eq!(
+            entry.addr(),
+            unsafe { br.pool_ptr().as_ptr() } as u64 + bid as u64 * BUFFER_SIZE as u64
+        );
+    }
+
+    // a completion on the other group reads the same memory and can move the buffers back
+    let moved = shared.get_buffers_range(2, 1).unwrap();
+    assert_eq!(moved[0], 0x2a);
+    let tail = br.tail();
+    shared.recycle_range_into(&br, &moved).unwrap();
+    assert_eq!(br.tail(), tail.wrapping_add(1));
+    assert_eq!(shared.in_flight(), 0);
+}
+
+#[test]
+fn test_sharing_ring_accounts_only_received_buffers() {
+    use io_uring_rb::{error::RingBufferError, recorder::BufRingRecorder};
+    use std::{cell::Cell, rc::Rc};
+
+    #[derive(Default)]
+    struct Starvations(Cell<u32>);
+
+    impl BufRingRecorder for Starvations {
+        fn on_issue(&self, _count: u16) {}
+        fn on_recycle(&self, _count: u16) {}
+        fn on_starvation(&self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let invalid = |err: std::io::Error| assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let other_ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+    let starvations = Rc::new(Starvations::default());
+    let shared = unsafe { RingBuffer::<BUFFER_SIZE, SIZE>::builder(0).share_pool_of(&br) }
+        .recorder(starvations.clone())
+        .build(&other_ring)
+        .unwrap();
+
+    let range = br.get_buffers_range(2, 2 * BUFFER_SIZE as usize).unwrap();
+    br.recycle_range_into(&shared, &range).unwrap();
+    assert_eq!((shared.provided(), shared.fill_estimate()), (0, 2));
+
+    // everything addressing the pool by position would reach the owner's buffers
+    invalid(shared.provide(1).unwrap_err());
+    invalid(shared.acquire_send_buffer(1).unwrap_err());
+    invalid(shared.fill_buffer(5, b"x").unwrap_err());
+    let tail = shared.tail();
+    invalid(shared.reset(&other_ring).unwrap_err());
+    assert_eq!(shared.tail(), tail);
+
+    // only the moved-in buffers are expected in its completions
+    let unexpected = shared.get_buffers_range_checked(5, 1).unwrap_err();
+    assert!(matches!(
+        unexpected.get_ref().and_then(|e| e.downcast_ref()),
+        Some(RingBufferError::UnexpectedBid(5))
+    ));
+    let _received = shared.get_buffers_range_checked(2, 1).unwrap();
+    assert_eq!((shared.in_flight(), shared.fill_estimate()), (1, 1));
+    assert!(!shared.is_starving());
+    assert_eq!(starvations.0.get(), 0);
+
+    // the held buffer is published again on this group, not leaked as a send buffer
+    assert_eq!(shared.recycle_all_outstanding(), 1);
+    assert_eq!(shared.tail(), tail.wrapping_add(1));
+    assert_eq!((shared.in_flight(), shared.fill_estimate()), (0, 2));
+
+    let (shared, err) = *shared.grow::<32>(&other_ring).map(|_| ()).unwrap_err();
+    invalid(err);
+    assert!(shared.is_registered());
+}
+
+#[test]
+fn test_shared_pool_rejects_pool_options() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+    let err = unsafe { RingBuffer::<BUFFER_SIZE, SIZE>::builder(1).share_pool_of(&br) }
+        .fill_pattern(0xdd)
+        .build(&ring)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let err = unsafe { RingBuffer::<BUFFER_SIZE, SIZE>::builder(1).share_pool_of(&br) }
+        .initial(SIZE)
+        .build(&ring)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_checked_range_rejects_unexpected_bids() {
+    use io_uring_rb::error::RingBufferError;
+
+    let unexpected = |err: std::io::Error| {
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        match err.get_ref().and_then(|e| e.downcast_ref()) {
+            Some(RingBufferError::UnexpectedBid(bid)) => *bid,
+            other => panic!("unexpected error {other:?}"),
+        }
+    };
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new_with_initial(&ring, 0, 0, 8).unwrap();
+
+    assert_eq!(
+        unexpected(br.get_buffers_range_checked(SIZE, 1).unwrap_err()),
+        SIZE
+    );
+    // buffer 8 was never provided
+    assert_eq!(
+        unexpected(
+            br.get_buffers_range_checked(7, BUFFER_SIZE as usize + 1)
+                .unwrap_err()
+        ),
+        8
+    );
+
+    let range = br.get_buffers_range_checked(2, 10).unwrap();
+    // a second completion for a buffer the app still holds
+    assert_eq!(
+        unexpected(br.get_buffers_range_checked(2, 10).unwrap_err()),
+        2
+    );
+    br.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_checked_last_buffer_index() {
+    type Br = RingBuffer<BUFFER_SIZE, SIZE>;
+    let buffer = BUFFER_SIZE as usize;
+
+    assert_eq!(Br::checked_last_buffer_index(0, 0), None);
+    assert_eq!(Br::checked_last_buffer_index(0, 1), Some(0));
+    assert_eq!(Br::checked_last_buffer_index(3, buffer), Some(3));
+    assert_eq!(Br::checked_last_buffer_index(3, buffer + 1), Some(4));
+    // spanning more than RING_SIZE buffers, the index isn't wrapped
+    assert_eq!(
+        Br::checked_last_buffer_index(SIZE - 1, 3 * POOL_BYTES),
+        Some(4 * SIZE - 2)
+    );
+
+    // bid near u16::MAX
+    assert_eq!(Br::checked_last_buffer_index(u16::MAX, 1), Some(u16::MAX));
+    assert_eq!(Br::checked_last_buffer_index(u16::MAX, buffer + 1), None);
+    assert_eq!(
+        Br::checked_last_buffer_index(u16::MAX - 1, 2 * buffer),
+        Some(u16::MAX)
+    );
+    assert_eq!(Br::checked_last_buffer_index(0, usize::MAX), None);
+}
+
+#[test]
+fn test_slots_for() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<BUFFER_SIZE, SIZE>::new(&ring, 0, 0).unwrap();
+    let buffer = BUFFER_SIZE as usize;
+
+    assert_eq!(br.slots_for(0), 0);
+    assert_eq!(br.slots_for(1), 1);
+    assert_eq!(br.slots_for(buffer), 1);
+    assert_eq!(br.slots_for(buffer + 1), 2);
+    assert_eq!(br.slots_for(POOL_BYTES), SIZE);
+    // past u16::MAX buffers the count saturates
+    assert_eq!(br.slots_for(u16::MAX as usize * buffer), u16::MAX);
+    assert_eq!(br.slots_for(u16::MAX as usize * buffer + 1), u16::MAX);
+    assert_eq!(br.slots_for(usize::MAX), u16::MAX);
+}
@@ -0,0 +1,26 @@
+use std::{io::Write, os::fd::AsRawFd};
+
+use io_uring::types::Fd;
+use io_uring_rb::{RingBuffer, recv_result::RecvResult};
+
+#[test]
+fn test_read_file_with_buffer_select() {
+    let mut ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 4>::new(&ring, 0, 0).unwrap();
+
+    let path = std::env::temp_dir().join(format!("io_uring_rb_read_{}", std::process::id()));
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(b"0123456789abcdefXYZ").unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    unsafe {
+        ring.submission()
+            .push(&br.read(Fd(file.as_raw_fd()), 16))
+            .unwrap()
+    };
+    ring.submit_and_wait(1).unwrap();
+    let (result, range) = br.drain_completions(ring.completion()).next().unwrap();
+    assert!(matches!(result, RecvResult::Data { len: 3, .. }));
+    assert_eq!(range.unwrap(), &b"XYZ"[..]);
+}
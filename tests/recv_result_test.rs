@@ -0,0 +1,29 @@
+use io_uring_rb::recv_result::{RecvResult, classify_recv};
+use rustix::io::Errno;
+
+const F_BUFFER: u32 = 1 << 0;
+const F_MORE: u32 = 1 << 1;
+
+#[test]
+fn test_classify_recv() {
+    assert_eq!(
+        classify_recv(100, F_BUFFER | F_MORE | (7 << 16)),
+        RecvResult::Data {
+            bid: 7,
+            len: 100,
+            more: true
+        }
+    );
+    assert_eq!(
+        classify_recv(1, F_BUFFER),
+        RecvResult::Data {
+            bid: 0,
+            len: 1,
+            more: false
+        }
+    );
+    assert_eq!(classify_recv(0, 0), RecvResult::Closed);
+    assert_eq!(classify_recv(-105, 0), RecvResult::NoBuffers);
+    assert_eq!(classify_recv(-104, 0), RecvResult::Err(Errno::CONNRESET));
+    assert_eq!(classify_recv(10, 0), RecvResult::Err(Errno::INVAL));
+}
@@ -135,3 +135,55 @@ pub mod tools {
         }
     }
 }
+
+#[test]
+fn test_for_each_recv() {
+    let data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+    let sent = data.clone();
+
+    let mut ring = io_uring::IoUring::new(64).unwrap();
+    let br = RingBuffer::<1024, 64>::new(&ring, 0, 0).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = thread::spawn(move || {
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(&sent).unwrap();
+    });
+    let (server, _) = listener.accept().unwrap();
+
+    let mut received = Vec::new();
+    br.for_each_recv(&mut ring, Fd(server.as_raw_fd()), |range| {
+        received.extend(Vec::from(range))
+    })
+    .unwrap();
+
+    handle.join().unwrap();
+    assert_eq!(received, data);
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_for_each_recv_cancels_on_invalid_completion() {
+    let mut ring = io_uring::IoUring::new(8).unwrap();
+    // group 0 of `ring` is a large ring, the RingBuffer handed to for_each_recv is a smaller one
+    // with the same group id on another ring: a 100 byte completion doesn't fit its 32 bytes pool
+    let _large = RingBuffer::<4096, 2>::new(&ring, 0, 0).unwrap();
+    let other_ring = io_uring::IoUring::new(8).unwrap();
+    let small = RingBuffer::<16, 2>::new(&other_ring, 0, 0).unwrap();
+    let (mut writer, socket) = std::os::unix::net::UnixStream::pair().unwrap();
+    writer.write_all(&[7; 100]).unwrap();
+
+    let err = small
+        .for_each_recv(&mut ring, Fd(socket.as_raw_fd()), |_| {
+            panic!("no range expected")
+        })
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // the multishot is gone: more data posts no CQE
+    writer.write_all(&[8; 100]).unwrap();
+    sleep(Duration::from_millis(50));
+    ring.submit().unwrap();
+    assert!(ring.completion().is_empty());
+}
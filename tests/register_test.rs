@@ -0,0 +1,835 @@
+use io_uring_rb::{MAX_RING_SIZE, RingBuffer, check_ring_alignment, max_ring_entries};
+
+#[test]
+fn test_max_ring_entries_probe() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let max = max_ring_entries(&ring).unwrap();
+    assert!(max.is_power_of_two());
+    assert!(max <= MAX_RING_SIZE);
+
+    // the probe leaves no group behind, the same id can be registered right after
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    assert_eq!(br.group_id(), 0);
+}
+
+#[test]
+fn test_read_only_pool_is_rejected_for_recv() {
+    use io_uring_rb::{ProtFlags, builder::MapOptions};
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let read_only = MapOptions {
+        pool_prot: ProtFlags::READ,
+        ..Default::default()
+    };
+    let err = RingBuffer::<64, 16>::builder(0)
+        .map_options(read_only)
+        .build(&ring)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    assert!(br.protect_pool(ProtFlags::READ).is_err());
+    br.unregister(&ring).unwrap();
+    br.protect_pool(ProtFlags::READ).unwrap();
+}
+
+#[test]
+fn test_grow_keeps_group_id() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_initial(&ring, 0, 3, 8).unwrap();
+
+    let buffer = br.get_buffer(0, 10).unwrap();
+    let (br, err) = *br.grow::<32>(&ring).map(|_| ()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(br.is_registered());
+    br.recycle_buffer(&buffer);
+
+    // a send buffer points into the pool grow would unmap
+    let send = br.acquire_send_buffer(8).unwrap();
+    assert_eq!(br.in_flight(), 0);
+    let (br, err) = *br.grow::<32>(&ring).map(|_| ()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    br.release_send_buffer(send);
+
+    let grown = br.grow::<32>(&ring).map_err(|failed| failed.1).unwrap();
+    assert_eq!(grown.group_id(), 3);
+    assert!(grown.is_registered());
+    assert_eq!(grown.provided(), 32);
+    assert!(io_uring_rb::group_id::GroupIdAllocator::is_used(&ring, 3));
+}
+
+#[test]
+fn test_new_with_fd() {
+    use std::os::fd::{AsRawFd, BorrowedFd};
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let ring_fd = unsafe { BorrowedFd::borrow_raw(ring.as_raw_fd()) };
+    let br = RingBuffer::<64, 16>::new_with_fd(ring_fd, 0, 5).unwrap();
+    assert!(br.is_registered());
+
+    // the group is live in the kernel, registering the same id again fails
+    let err = RingBuffer::<64, 16>::new(&ring, 0, 5).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+    assert!(matches!(
+        err.get_ref().and_then(|e| e.downcast_ref()),
+        Some(io_uring_rb::error::RingBufferError::GroupIdInUse(5))
+    ));
+    br.unregister(&ring).unwrap();
+}
+
+#[test]
+fn test_fill_buffer_only_outside_the_kernel() {
+    use io_uring::types::Fd;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_empty(&ring, 0, 0).unwrap();
+    assert_eq!(br.provide(8).unwrap(), 8);
+
+    assert!(br.fill_buffer(0, b"recv").is_err());
+    assert!(br.fill_buffer(15, &[0; 65]).is_err());
+    br.fill_buffer(15, b"static content").unwrap();
+
+    let data = unsafe { std::slice::from_raw_parts(br.pool_ptr().as_ptr().add(15 * 64), 14) };
+    assert_eq!(data, b"static content");
+    assert!(br.send_slot(Fd(0), 15, 14).is_some());
+    assert!(br.send_slot(Fd(0), 16, 14).is_none());
+}
+
+#[test]
+fn test_provide_buf_ring() {
+    use io_uring_rb::{error::RingBufferError, ext::IoUringBufRingExt, group_id::GroupIdAllocator};
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = ring.provide_buf_ring::<64, 16>(4, 0).unwrap();
+    assert!(br.is_registered());
+    assert_eq!((br.group_id(), br.tail()), (4, 16));
+    assert!(GroupIdAllocator::is_used(&ring, 4));
+
+    let err = ring.provide_buf_ring::<64, 16>(4, 0).unwrap_err();
+    assert!(matches!(
+        err.get_ref().and_then(|e| e.downcast_ref()),
+        Some(RingBufferError::GroupIdInUse(4))
+    ));
+}
+
+#[test]
+fn test_new_with_submitter() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_submitter(&ring.submitter(), 0, 7).unwrap();
+    assert!(br.is_registered());
+    assert!(!io_uring_rb::group_id::GroupIdAllocator::is_used(&ring, 7));
+    br.unregister(&ring).unwrap();
+}
+
+#[test]
+fn test_failed_registration_leaves_nothing_behind() {
+    use io_uring_rb::group_id::GroupIdAllocator;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    // no such flag, the kernel refuses the registration
+    assert!(RingBuffer::<64, 16>::new(&ring, 0x8000, 9).is_err());
+    assert!(!GroupIdAllocator::is_used(&ring, 9));
+
+    // the group id is still free in the kernel too
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 9).unwrap();
+    assert_eq!(br.tail(), 16);
+}
+
+#[test]
+fn test_new_with_initial() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_initial(&ring, 0, 0, 6).unwrap();
+    assert_eq!(br.tail(), 6);
+    assert_eq!(br.provided(), 6);
+    assert_eq!(br.provide(16).unwrap(), 10);
+    assert_eq!(br.tail(), 16);
+
+    let err = RingBuffer::<64, 16>::new_with_initial(&ring, 0, 1, 17).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!io_uring_rb::group_id::GroupIdAllocator::is_used(&ring, 1));
+}
+
+#[test]
+fn test_debug_shows_group_and_counters() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 9).unwrap();
+    let range = br.get_buffers_range(0, 3 * 64).unwrap();
+
+    let debug = format!("{br:?}");
+    assert!(debug.starts_with("RingBuffer {"));
+    for field in [
+        "group_id: 9",
+        "buffer_size: 64",
+        "ring_size: 16",
+        "pool_bytes: 1024",
+        "tail: 16",
+        "in_flight: 3",
+    ] {
+        assert!(debug.contains(field), "{field} missing from {debug}");
+    }
+    let pool = unsafe { br.pool_ptr() }.as_ptr() as usize;
+    assert!(debug.contains(&format!("pool_base: {pool:#x}")));
+
+    // the counters follow the ring
+    br.recycle_buffers_range(&range);
+    let debug = format!("{br:?}");
+    assert!(debug.contains("tail: 19") && debug.contains("in_flight: 0"));
+}
+
+#[test]
+fn test_flags_are_queryable() {
+    use io_uring_rb::flags::BufRingFlags;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_flags(&ring, BufRingFlags::INC, 0).unwrap();
+    assert_eq!(br.flags(), BufRingFlags::INC.bits());
+    assert!(br.ring_flags().contains(BufRingFlags::INC));
+
+    br.unregister(&ring).unwrap();
+    br.reregister(&ring, 0).unwrap();
+    assert!(br.ring_flags().is_empty());
+}
+
+#[test]
+fn test_write_into_send_buffer() {
+    use std::io::Write;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 16>::new_empty(&ring, 0, 0).unwrap();
+    let mut buffer = br.acquire_send_buffer(0).unwrap();
+
+    write!(buffer, "HTTP/1.1 {}", 200).unwrap();
+    assert_eq!(buffer.as_ref(), b"HTTP/1.1 200");
+    assert_eq!(buffer.write(b"\r\n\r\n").unwrap(), 4);
+    assert_eq!(buffer.write(b"body").unwrap(), 0);
+    assert_eq!(
+        buffer.write_all(b"body").unwrap_err().kind(),
+        std::io::ErrorKind::WriteZero
+    );
+    assert_eq!(buffer.as_ref().len(), 16);
+    br.release_send_buffer(buffer);
+}
+
+#[test]
+fn test_send_buffers_stay_out_of_recv_accounting() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 16>::builder(0)
+        .initial(8)
+        .low_water_mark(7)
+        .build(&ring)
+        .unwrap();
+    let held = br.get_buffer(0, 4).unwrap();
+    assert_eq!((br.in_flight(), br.fill_estimate()), (1, 7));
+
+    let sends: Vec<_> = (0..4)
+        .map(|_| br.acquire_send_buffer(16).unwrap())
+        .collect();
+    assert!(sends.iter().all(|b| br.is_issued(b.bid())));
+    assert_eq!((br.in_flight(), br.fill_estimate()), (1, 7));
+    assert_eq!(br.high_water_mark(), 1);
+    assert!(br.has_capacity(7));
+    // provide stops before the send buffers at the end of the pool
+    assert_eq!(br.provide(8).unwrap(), 4);
+
+    for buffer in sends {
+        br.release_send_buffer(buffer);
+    }
+    assert_eq!(br.in_flight(), 1);
+    br.recycle_buffer(&held);
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_misaligned_ring_is_rejected() {
+    use io_uring_rb::error::RingBufferError;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    let entries = unsafe { br.ring_entries_ptr() };
+    check_ring_alignment(entries.as_ptr()).unwrap();
+
+    // one entry in, still aligned for a BufRingEntry but not on a page
+    let misaligned = unsafe { entries.as_ptr().add(1) };
+    let page_size = rustix::param::page_size();
+    match check_ring_alignment(misaligned) {
+        Err(RingBufferError::RingMisaligned { addr, page_size: p }) => {
+            assert_eq!((addr, p), (misaligned as usize, page_size))
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+    let err: std::io::Error = check_ring_alignment(misaligned).unwrap_err().into();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_reset_rearms_the_whole_ring() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_initial(&ring, 0, 0, 8).unwrap();
+    let _held = br.get_buffers_range(0, 3 * 64).unwrap();
+    let send = br.acquire_send_buffer(8).unwrap();
+
+    // the send buffer would be handed out again while still owned by its Send SQE
+    let err = br.reset(&ring).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::ResourceBusy);
+    assert_eq!(br.in_flight(), 3);
+    br.release_send_buffer(send);
+
+    br.reset(&ring).unwrap();
+    assert!(br.is_registered());
+    assert_eq!(br.tail(), 16);
+    assert_eq!(br.in_flight(), 0);
+    assert_eq!(br.provided(), 16);
+    assert_eq!(br.free_bids().count(), 16);
+}
+
+#[test]
+fn test_free_bids_skip_issued_ones() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+
+    // a wrapping range (15, 0) and a single buffer
+    let range = br.get_buffers_range(15, 100).unwrap();
+    let buffer = br.get_buffer(4, 10).unwrap();
+    assert!(br.is_issued(0) && br.is_issued(4) && br.is_issued(15));
+    assert_eq!(
+        br.free_bids().collect::<Vec<_>>(),
+        [1, 2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14]
+    );
+
+    br.recycle_buffers_range(&range);
+    assert_eq!(
+        br.free_bids().collect::<Vec<_>>(),
+        [0, 1, 2, 3, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]
+    );
+    br.recycle_buffer(&buffer);
+    assert!(br.free_bids().eq(0..16));
+}
+
+#[test]
+fn test_fill_pattern() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::builder(0)
+        .fill_pattern(0xDD)
+        .build(&ring)
+        .unwrap();
+    let pool = unsafe { std::slice::from_raw_parts(br.pool_ptr().as_ptr(), br.pool_len()) };
+    assert!(pool.iter().all(|&b| b == 0xDD));
+
+    let zeroed = RingBuffer::<64, 16>::new(&ring, 0, 1).unwrap();
+    let pool = unsafe { std::slice::from_raw_parts(zeroed.pool_ptr().as_ptr(), zeroed.pool_len()) };
+    assert!(pool.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_set_advertised_len() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    assert_eq!(br.advertised_len(), 64);
+
+    br.set_advertised_len(16);
+    assert_eq!(br.advertised_len(), 16);
+    let buffer = br.get_buffer(3, 10).unwrap();
+    let tail = br.tail();
+    br.recycle_buffer(&buffer);
+    let entry = unsafe { &*br.ring_entries_ptr().as_ptr().add((tail % 16) as usize) };
+    assert_eq!((entry.bid(), entry.len()), (3, 16));
+
+    br.set_advertised_len(1000);
+    assert_eq!(br.advertised_len(), 64);
+
+    // the stride is still BUFFER_SIZE
+    let range = br.get_buffers_range(5, 100).unwrap();
+    assert_eq!(br.buffer_ids_of(&range).collect::<Vec<_>>(), [5, 6]);
+    br.recycle_buffers_range(&range);
+}
+
+#[test]
+fn test_entry_len_and_recycle_with_len() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::with_entry_len(&ring, 0, 0, 48).unwrap();
+    assert_eq!(br.advertised_len(), 48);
+    // every entry published at registration advertises the entry len
+    for slot in 0..16 {
+        let entry = unsafe { &*br.ring_entries_ptr().as_ptr().add(slot) };
+        assert_eq!((entry.bid(), entry.len()), (slot as u16, 48));
+    }
+
+    let entry_at =
+        |tail: u16| unsafe { &*br.ring_entries_ptr().as_ptr().add((tail % 16) as usize) };
+    let buffer = br.get_buffer(7, 30).unwrap();
+    let tail = br.tail();
+    br.recycle_buffer_with_len(&buffer, 20);
+    assert_eq!(br.tail(), tail.wrapping_add(1));
+    assert_eq!((entry_at(tail).bid(), entry_at(tail).len()), (7, 20));
+    // the custom length is for that recycle only
+    assert_eq!(br.advertised_len(), 48);
+
+    // clamped to BUFFER_SIZE, like the entry len
+    let buffer = br.get_buffer(8, 30).unwrap();
+    let tail = br.tail();
+    br.recycle_buffer_with_len(&buffer, 1000);
+    assert_eq!((entry_at(tail).bid(), entry_at(tail).len()), (8, 64));
+    assert_eq!(br.in_flight(), 0);
+
+    let clamped = RingBuffer::<64, 16>::with_entry_len(&ring, 0, 1, 1000).unwrap();
+    assert_eq!(clamped.advertised_len(), 64);
+}
+
+#[test]
+fn test_is_starving_at_the_low_water_mark() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::builder(0)
+        .low_water_mark(4)
+        .build(&ring)
+        .unwrap();
+    // 11 buffers left to the kernel, then 5: one over the mark
+    let first = br.get_buffers_range(0, 5 * 64).unwrap();
+    assert_eq!(br.fill_estimate(), 11);
+    assert!(!br.is_starving());
+    let second = br.get_buffers_range(5, 6 * 64).unwrap();
+    assert_eq!(br.fill_estimate(), 5);
+    assert!(!br.is_starving());
+
+    // starving right at the mark and below it
+    let third = br.get_buffer(11, 1).unwrap();
+    assert_eq!(br.fill_estimate(), 4);
+    assert!(br.is_starving());
+    let fourth = br.get_buffer(12, 1).unwrap();
+    assert!(br.is_starving());
+
+    br.recycle_buffer(&fourth);
+    br.recycle_buffer(&third);
+    assert!(!br.is_starving());
+    br.recycle_buffers_range(&second);
+    br.recycle_buffers_range(&first);
+    assert_eq!(br.fill_estimate(), 16);
+
+    // with the default mark of 0 only an empty ring starves
+    let default = RingBuffer::<64, 16>::new(&ring, 0, 1).unwrap();
+    let all = default.get_buffers_range(0, 15 * 64).unwrap();
+    assert!(!default.is_starving());
+    let last = default.get_buffer(15, 1).unwrap();
+    assert!(default.is_starving());
+    default.recycle_buffer(&last);
+    default.recycle_buffers_range(&all);
+}
+
+#[test]
+fn test_has_capacity() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_initial(&ring, 0, 0, 8).unwrap();
+    assert!(br.has_capacity(8));
+    assert!(!br.has_capacity(9));
+
+    let range = br.get_buffers_range(0, 3 * 64).unwrap();
+    assert!(br.has_capacity(5));
+    assert!(!br.has_capacity(6));
+    br.recycle_buffers_range(&range);
+    assert!(br.has_capacity(8));
+}
+
+#[test]
+fn test_descriptor_round_trip() {
+    use io_uring_rb::flags::BufRingFlags;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new_with_flags(&ring, BufRingFlags::INC, 3).unwrap();
+    let descriptor = br.descriptor();
+    assert_eq!(
+        (
+            descriptor.group_id,
+            descriptor.ring_size,
+            descriptor.buffer_size
+        ),
+        (3, 16, 64)
+    );
+
+    let _held = br.get_buffer(2, 64).unwrap();
+    unsafe { br.pool_ptr().as_ptr().write_bytes(0xab, br.pool_len()) };
+
+    // a restart: the old group goes away, an equivalent one takes its place
+    br.unregister(&ring).unwrap();
+    drop(br);
+    let restored = RingBuffer::<64, 16>::from_descriptor(&ring, &descriptor).unwrap();
+    assert_eq!(restored.descriptor(), descriptor);
+    // only the shape comes back: a zeroed pool, fully provided, nothing checked out
+    let pool =
+        unsafe { std::slice::from_raw_parts(restored.pool_ptr().as_ptr(), restored.pool_len()) };
+    assert!(pool.iter().all(|&b| b == 0));
+    assert_eq!((restored.provided(), restored.tail()), (16, 16));
+    assert_eq!(restored.in_flight(), 0);
+    assert!(!restored.is_issued(2));
+
+    let err = RingBuffer::<128, 16>::from_descriptor(&ring, &descriptor).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_high_water_mark() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    assert_eq!(br.high_water_mark(), 0);
+
+    let first = br.get_buffers_range(0, 3 * 64).unwrap();
+    let second = br.get_buffers_range(3, 64).unwrap();
+    br.recycle_buffers_range(&first);
+    assert_eq!(br.in_flight(), 1);
+    assert_eq!(br.high_water_mark(), 4);
+
+    br.reset_high_water_mark();
+    assert_eq!(br.high_water_mark(), 1);
+    br.recycle_buffers_range(&second);
+    assert_eq!(br.high_water_mark(), 1);
+}
+
+#[test]
+fn test_lazy_pool_prefault_buffers() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<4096, 16>::builder(0)
+        .populate(false)
+        .build(&ring)
+        .unwrap();
+    br.prefault_buffers(2..5).unwrap();
+    br.prefault_buffers(0..16).unwrap();
+    br.prefault_buffers(3..3).unwrap();
+    assert_eq!(
+        br.prefault_buffers(8..17).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn test_recycle_by_value() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    let buffer = br.get_buffer(4, 10).unwrap();
+    let tail = br.tail();
+    br.recycle_owned(buffer);
+    assert_eq!(br.tail(), tail.wrapping_add(1));
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_try_recycle_buffer_out_of_ring() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let large = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    let small = RingBuffer::<64, 8>::new(&ring, 0, 1).unwrap();
+
+    // bid 12 exists in the large ring only
+    let buffer = large.get_buffer(12, 10).unwrap();
+    let tail = small.tail();
+    let err = small.try_recycle_buffer(&buffer).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!((small.tail(), small.in_flight()), (tail, 0));
+
+    let tail = large.tail();
+    large.try_recycle_buffer(&buffer).unwrap();
+    assert_eq!(large.tail(), tail.wrapping_add(1));
+    assert_eq!(large.in_flight(), 0);
+}
+
+#[test]
+fn test_mlock_locks_or_fails_cleanly() {
+    use io_uring_rb::error::RingBufferError;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    match RingBuffer::<4096, 16>::builder(0).mlock(true).build(&ring) {
+        Ok(br) => {
+            assert!(br.is_registered());
+            let status = std::fs::read_to_string("/proc/self/status").unwrap();
+            let locked_kb: usize = status
+                .lines()
+                .find_map(|l| l.strip_prefix("VmLck:"))
+                .and_then(|l| l.trim().trim_end_matches("kB").trim().parse().ok())
+                .unwrap();
+            assert!(locked_kb * 1024 >= br.pool_len());
+            br.unregister(&ring).unwrap();
+        }
+        // over RLIMIT_MEMLOCK, or not allowed to lock at all
+        Err(err) => match err.get_ref().and_then(|e| e.downcast_ref()) {
+            Some(RingBufferError::Mlock { source, .. }) => assert!(matches!(
+                source.raw_os_error(),
+                Some(libc::ENOMEM | libc::EPERM)
+            )),
+            other => panic!("unexpected error {other:?}"),
+        },
+    }
+    // the group id is free again either way
+    RingBuffer::<4096, 16>::new(&ring, 0, 0).unwrap();
+}
+
+/// the mapping `addr` falls in, from /proc/self/maps
+fn mapping_of(addr: usize) -> Option<std::ops::Range<usize>> {
+    std::fs::read_to_string("/proc/self/maps")
+        .unwrap()
+        .lines()
+        .filter_map(|l| l.split_once(' ')?.0.split_once('-'))
+        .map(|(start, end)| {
+            let parse = |s| usize::from_str_radix(s, 16).unwrap();
+            parse(start)..parse(end)
+        })
+        .find(|range| range.contains(&addr))
+}
+
+/// the permission column of the mapping `addr` falls in, e.g. "rw-p", from /proc/self/maps
+fn perms_of(addr: usize) -> String {
+    std::fs::read_to_string("/proc/self/maps")
+        .unwrap()
+        .lines()
+        .find_map(|l| {
+            let mut fields = l.split(' ');
+            let (start, end) = fields.next()?.split_once('-')?;
+            let parse = |s| usize::from_str_radix(s, 16).unwrap();
+            (parse(start)..parse(end))
+                .contains(&addr)
+                .then(|| fields.next().unwrap().to_owned())
+        })
+        .unwrap()
+}
+
+/// the VmFlags of the mapping starting at `start`, from /proc/self/smaps
+fn vm_flags_of(start: usize) -> Vec<String> {
+    let smaps = std::fs::read_to_string("/proc/self/smaps").unwrap();
+    let header = format!("{start:x}-");
+    smaps
+        .split_once(&*header)
+        .and_then(|(_, mapping)| mapping.lines().find_map(|l| l.strip_prefix("VmFlags:")))
+        .unwrap()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(feature = "guard-pages")]
+#[test]
+fn test_pool_sits_between_guard_pages() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let page = rustix::param::page_size();
+    // 100 bytes buffers: the pool doesn't end on a page boundary
+    let br = RingBuffer::<100, 16>::new(&ring, 0, 0).unwrap();
+    let pool = unsafe { br.pool_ptr() }.as_ptr() as usize;
+    let end = pool + br.pool_len();
+    assert!(pool.is_multiple_of(page));
+
+    assert_eq!(perms_of(pool - 1), "---p");
+    assert_eq!(perms_of(pool - page), "---p");
+    assert_eq!(perms_of(pool), "rw-p");
+    assert_eq!(perms_of(end - 1), "rw-p");
+    // the guard starts on the page after the pool
+    let guard = end.next_multiple_of(page);
+    assert_eq!(perms_of(guard), "---p");
+    assert_eq!(perms_of(guard + page - 1), "---p");
+}
+
+#[test]
+fn test_advise_dont_fork() {
+    use io_uring_rb::Advice;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<4096, 16>::new(&ring, 0, 0).unwrap();
+    let pool = unsafe { br.pool_ptr() }.as_ptr() as usize;
+    let start = mapping_of(pool).unwrap().start;
+    // "dc": don't copy on fork, the child wouldn't share the recv buffers
+    assert!(!vm_flags_of(start).contains(&"dc".to_owned()));
+
+    br.advise(Advice::LinuxDontFork).unwrap();
+    let start = mapping_of(pool).unwrap().start;
+    assert!(vm_flags_of(start).contains(&"dc".to_owned()));
+
+    br.advise(Advice::LinuxDoFork).unwrap();
+    let start = mapping_of(pool).unwrap().start;
+    assert!(!vm_flags_of(start).contains(&"dc".to_owned()));
+}
+
+#[test]
+fn test_shared_map_option() {
+    use io_uring_rb::builder::MapOptions;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let shared = MapOptions {
+        shared: true,
+        ..Default::default()
+    };
+    let br = RingBuffer::<64, 16>::builder(0)
+        .map_options(shared)
+        .build(&ring)
+        .unwrap();
+    let pool = unsafe { br.pool_ptr() }.as_ptr() as usize;
+    let entries = unsafe { br.ring_entries_ptr() }.as_ptr() as usize;
+    assert_eq!(perms_of(pool), "rw-s");
+    assert_eq!(perms_of(entries), "rw-s");
+
+    let private = RingBuffer::<64, 16>::new(&ring, 0, 1).unwrap();
+    assert_eq!(
+        perms_of(unsafe { private.pool_ptr() }.as_ptr() as usize),
+        "rw-p"
+    );
+
+    // neither a single mapping nor mlock goes with MAP_SHARED
+    for builder in [
+        RingBuffer::<64, 16>::builder(2).single_mapping(true),
+        RingBuffer::<64, 16>::builder(2).mlock(true),
+    ] {
+        let err = builder.map_options(shared).build(&ring).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+    // refused before registering, the group id is still free
+    RingBuffer::<64, 16>::new(&ring, 0, 2).unwrap();
+}
+
+#[test]
+fn test_hugetlb_map_option() {
+    use io_uring_rb::{builder::MapOptions, error::RingBufferError};
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let built = RingBuffer::<4096, 512>::builder(0)
+        .map_options(MapOptions {
+            hugetlb: true,
+            ..Default::default()
+        })
+        .build(&ring);
+    match built {
+        // guard pages would have to be mprotected inside a huge page
+        Err(e) if cfg!(feature = "guard-pages") => {
+            assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput)
+        }
+        // only the pool is on huge pages, the ring entries stay on regular ones
+        Ok(br) => {
+            let pool = unsafe { br.pool_ptr() }.as_ptr() as usize;
+            let entries = unsafe { br.ring_entries_ptr() }.as_ptr() as usize;
+            assert!(
+                mapping_of(pool).unwrap() != mapping_of(entries).unwrap()
+                    && pool.is_multiple_of(2 << 20)
+            );
+            br.unregister(&ring).unwrap();
+        }
+        // no huge page reserved (vm.nr_hugepages = 0): the pool mmap fails with a typed error
+        Err(e) => match e.get_ref().and_then(|e| e.downcast_ref()) {
+            Some(RingBufferError::PoolMmap { source, .. }) => {
+                assert_eq!(source.raw_os_error(), Some(libc::ENOMEM))
+            }
+            other => panic!("unexpected error {other:?}"),
+        },
+    }
+    // the group id is free again either way
+    RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+}
+
+#[test]
+fn test_single_mapping_layout() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::builder(0)
+        .single_mapping(true)
+        .build(&ring)
+        .unwrap();
+    let entries = unsafe { br.ring_entries_ptr() }.as_ptr() as usize;
+    let pool = unsafe { br.pool_ptr() }.as_ptr() as usize;
+    // the pool is the trailer of the ring mapping, on the first page after the 16 entries
+    assert_eq!(pool, entries + rustix::param::page_size());
+    let mapping = mapping_of(entries).unwrap();
+    assert!(mapping.contains(&pool) && mapping.end >= pool + br.pool_len());
+
+    let buffer = br.get_buffer(15, 64).unwrap();
+    let data = buffer.as_ref().as_ptr() as usize;
+    assert!((pool..pool + br.pool_len()).contains(&data));
+    br.recycle_buffer(&buffer);
+
+    // the pool doesn't own its memory: dropping unmaps the region once, through the ring, and
+    // the same group builds again on a fresh mapping
+    br.unregister(&ring).unwrap();
+    drop(br);
+    let again = RingBuffer::<64, 16>::builder(0)
+        .single_mapping(true)
+        .build(&ring)
+        .unwrap();
+    let pool = unsafe { std::slice::from_raw_parts(again.pool_ptr().as_ptr(), again.pool_len()) };
+    assert!(pool.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn test_new_auto_picks_free_group_ids() {
+    use io_uring_rb::group_id::GroupIdAllocator;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let fixed = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    let first = RingBuffer::<64, 16>::new_auto(&ring, 0).unwrap();
+    let second = RingBuffer::<64, 16>::new_auto(&ring, 0).unwrap();
+    assert_eq!(
+        (fixed.group_id(), first.group_id(), second.group_id()),
+        (0, 1, 2)
+    );
+    assert!(first.is_registered() && second.is_registered());
+
+    // the allocator is per ring
+    let other_ring = io_uring::IoUring::new(8).unwrap();
+    assert_eq!(
+        RingBuffer::<64, 16>::new_auto(&other_ring, 0)
+            .unwrap()
+            .group_id(),
+        0
+    );
+
+    // a released id is handed out again
+    first.unregister(&ring).unwrap();
+    drop(first);
+    assert!(!GroupIdAllocator::is_used(&ring, 1));
+    assert_eq!(
+        RingBuffer::<64, 16>::new_auto(&ring, 0).unwrap().group_id(),
+        1
+    );
+}
+
+#[test]
+fn test_new_auto_skips_untracked_group_ids() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let max = max_ring_entries(&ring).unwrap();
+    // registered behind the allocator's back: group 0 looks free but the kernel answers EEXIST
+    let untracked = RingBuffer::<64, 16>::new_with_submitter(&ring.submitter(), 0, 0).unwrap();
+    assert_eq!(max_ring_entries(&ring).unwrap(), max);
+    let auto = RingBuffer::<64, 16>::new_auto(&ring, 0).unwrap();
+    assert_eq!((untracked.group_id(), auto.group_id()), (0, 1));
+}
+
+#[test]
+fn test_reregister_moves_the_group_id_reservation() {
+    use io_uring_rb::group_id::GroupIdAllocator;
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let other_ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 3).unwrap();
+    br.unregister(&ring).unwrap();
+    br.reregister(&other_ring, 0).unwrap();
+    assert!(!GroupIdAllocator::is_used(&ring, 3));
+    assert!(GroupIdAllocator::is_used(&other_ring, 3));
+    assert_eq!(GroupIdAllocator::next_free(&ring), Some(0));
+
+    // the id is free again on the first ring, and released from the second on drop
+    let again = RingBuffer::<64, 16>::new(&ring, 0, 3).unwrap();
+    br.unregister(&other_ring).unwrap();
+    drop(br);
+    assert!(!GroupIdAllocator::is_used(&other_ring, 3));
+    assert!(GroupIdAllocator::is_used(&ring, again.group_id()));
+}
+
+#[test]
+fn test_on_recycle_runs_once_per_bid() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<64, 16>::new(&ring, 0, 0).unwrap();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = seen.clone();
+    br.set_on_recycle(move |bid| log.borrow_mut().push(bid));
+
+    let buffer = br.get_buffer(4, 10).unwrap();
+    br.recycle_buffer(&buffer);
+    // a range wrapping at the end of the ring
+    let range = br.get_buffers_range(14, 3 * 64).unwrap();
+    br.recycle_buffers_range(&range);
+    assert_eq!(*seen.borrow(), [4, 14, 15, 0]);
+
+    br.clear_on_recycle();
+    let buffer = br.get_buffer(5, 10).unwrap();
+    br.recycle_buffer(&buffer);
+    assert_eq!(seen.borrow().len(), 4);
+}
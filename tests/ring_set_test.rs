@@ -0,0 +1,50 @@
+use io_uring_rb::ring_set::BufferRingSet;
+
+#[test]
+fn test_ring_set_routes_by_group_id() {
+    let mut set = BufferRingSet::new(io_uring::IoUring::new(8).unwrap());
+    set.add::<64, 16>(1, 0).unwrap();
+    set.add::<4096, 8>(2, 0).unwrap();
+    assert!(set.add::<64, 16>(1, 0).is_err());
+
+    let small = set.group(1).unwrap();
+    let large = set.group(2).unwrap();
+    assert_eq!((small.buffer_size(), small.ring_size()), (64, 16));
+    assert_eq!((large.buffer_size(), large.ring_size()), (4096, 8));
+
+    let range = set.data(2, 3, 100).unwrap();
+    assert_eq!(
+        (range.group_id(), range.first_bid(), range.len()),
+        (2, 3, 100)
+    );
+    assert_eq!(range.as_parts().0.len(), 100);
+    assert_eq!(range.as_parts().1, None);
+    assert_eq!((large.in_flight(), small.in_flight()), (1, 0));
+    set.recycle(range).unwrap();
+    assert_eq!(large.in_flight(), 0);
+
+    // a range recycled straight to another group is refused, nothing is recycled
+    let range = set.data(2, 0, 10).unwrap();
+    let err = small.recycle(range).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    assert_eq!((large.in_flight(), small.in_flight()), (1, 0));
+
+    assert!(set.data(3, 0, 1).is_none());
+    assert!(set.data(1, 16, 1).is_none());
+}
+
+#[test]
+fn test_ring_set_bundle_completion() {
+    let mut set = BufferRingSet::new(io_uring::IoUring::new(8).unwrap());
+    set.add::<64, 16>(1, 0).unwrap();
+    let group = set.group(1).unwrap();
+
+    // three buffers from bid 14, wrapping to buffer 0
+    let range = set.data(1, 14, 3 * 64).unwrap();
+    let (first, second) = range.as_parts();
+    assert_eq!((first.len(), second.map(<[u8]>::len)), (128, Some(64)));
+    assert_eq!(group.in_flight(), 3);
+
+    set.recycle(range).unwrap();
+    assert_eq!(group.in_flight(), 0);
+}
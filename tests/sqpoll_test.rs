@@ -0,0 +1,32 @@
+use std::os::{fd::AsRawFd, unix::net::UnixDatagram};
+
+use io_uring::types::Fd;
+use io_uring_rb::{RingBuffer, recv_result::RecvResult};
+
+/// the SQPOLL thread reads the buffer ring tail concurrently with our recycles
+#[test]
+fn test_recycle_with_sqpoll_ring() {
+    let mut ring = match io_uring::IoUring::builder().setup_sqpoll(1000).build(8) {
+        Ok(ring) => ring,
+        // SQPOLL needs CAP_SYS_NICE before 5.11
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return,
+        Err(e) => panic!("{e}"),
+    };
+    let br = RingBuffer::<64, 2>::new(&ring, 0, 0).unwrap();
+    let (tx, rx) = UnixDatagram::pair().unwrap();
+
+    // twice the ring size, every buffer goes back through the ring
+    for i in 0..4u8 {
+        tx.send(&[i; 8]).unwrap();
+        let user_data = br.push_recv_batch(&mut ring.submission(), Fd(rx.as_raw_fd()), 1, 0);
+        ring.submit_and_wait(1).unwrap();
+        let (_, result, buffer) = br
+            .collect_recv_batch(ring.completion(), user_data)
+            .next()
+            .unwrap();
+        assert!(matches!(result, RecvResult::Data { len: 8, .. }));
+        let buffer = buffer.unwrap();
+        assert_eq!(buffer, &[i; 8][..]);
+        br.recycle_buffer(&buffer);
+    }
+}
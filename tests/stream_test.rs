@@ -0,0 +1,95 @@
+#![cfg(feature = "stream")]
+
+use std::{
+    future::poll_fn,
+    io::Write,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    pin::Pin,
+    time::Duration,
+};
+
+use futures_core::Stream;
+use io_uring_rb::{RingBuffer, stream::BufferRangeStream};
+
+async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// runs `f` on a current thread runtime, a stream that stops making progress fails the test
+fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(async {
+            tokio::time::timeout(Duration::from_secs(10), f)
+                .await
+                .expect("stream stalled")
+        })
+}
+
+#[test]
+fn test_stream_yields_ranges_in_order_until_eof() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    // two buffers for 64 bytes: the multishot recv runs out of buffers (ENOBUFS, no F_MORE)
+    // and has to be re-armed by the stream once the ranges are recycled
+    let br = RingBuffer::<16, 2>::new(&ring, 0, 0).unwrap();
+    let (mut writer, reader) = UnixStream::pair().unwrap();
+    let data: Vec<u8> = (0..64).collect();
+    writer.write_all(&data).unwrap();
+
+    block_on(async {
+        let mut stream = BufferRangeStream::new(ring, &br, reader.as_raw_fd()).unwrap();
+        let mut received = Vec::new();
+        let mut ranges = 0;
+        while received.len() < data.len() {
+            let range = next(&mut stream).await.unwrap().unwrap();
+            received.extend(Vec::from(&range));
+            br.recycle_buffers_range(&range);
+            ranges += 1;
+        }
+        assert_eq!(received, data);
+        assert!(ranges >= 4);
+
+        drop(writer);
+        assert!(next(&mut stream).await.is_none());
+        assert!(next(&mut stream).await.is_none());
+    });
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_owned_stream_recycles() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 4>::new(&ring, 0, 0).unwrap();
+    let (mut writer, reader) = UnixStream::pair().unwrap();
+    writer.write_all(b"hello owned stream").unwrap();
+    drop(writer);
+
+    block_on(async {
+        let mut stream = BufferRangeStream::new(ring, &br, reader.as_raw_fd())
+            .unwrap()
+            .owned();
+        let mut received = Vec::new();
+        while let Some(chunk) = next(&mut stream).await {
+            received.extend(chunk.unwrap());
+        }
+        assert_eq!(received, b"hello owned stream");
+    });
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_stream_surfaces_recv_errors() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 4>::new(&ring, 0, 0).unwrap();
+    // recv on something that isn't a socket completes with -ENOTSOCK
+    let file = std::fs::File::open("/dev/null").unwrap();
+
+    block_on(async {
+        let mut stream = BufferRangeStream::new(ring, &br, file.as_raw_fd()).unwrap();
+        let err = next(&mut stream).await.unwrap().unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::ENOTSOCK));
+    });
+}
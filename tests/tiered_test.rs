@@ -0,0 +1,66 @@
+use io_uring_rb::tiered::{TieredBufferRing, TieredRange};
+
+type Tiers = TieredBufferRing<64, 16, 4096, 4>;
+
+#[test]
+fn test_tiered_routes_by_group_id() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    assert!(Tiers::new(&ring, 0, 1, 1).is_err());
+    let tiers = Tiers::new(&ring, 0, 1, 2).unwrap();
+    assert_eq!((tiers.small_group_id(), tiers.large_group_id()), (1, 2));
+
+    let header = tiers.get_buffers_range(1, 3, 40).unwrap();
+    assert!(matches!(header, TieredRange::Small(_)));
+    assert_eq!(header.as_parts().0.len(), 40);
+    assert_eq!(
+        (tiers.small().in_flight(), tiers.large().in_flight()),
+        (1, 0)
+    );
+
+    let body = tiers.get_buffers_range(2, 1, 6000).unwrap();
+    assert!(matches!(body, TieredRange::Large(_)));
+    assert_eq!(body.len(), 6000);
+    assert_eq!(
+        (tiers.small().in_flight(), tiers.large().in_flight()),
+        (1, 2)
+    );
+
+    assert!(tiers.get_buffers_range(3, 0, 10).is_none());
+}
+
+#[test]
+fn test_tiered_recycles_to_its_tier() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let tiers = Tiers::new(&ring, 0, 1, 2).unwrap();
+    let (small_tail, large_tail) = (tiers.small().tail(), tiers.large().tail());
+
+    let header = tiers.get_buffers_range(1, 5, 100).unwrap();
+    let body = tiers.get_buffers_range(2, 0, 4096).unwrap();
+
+    tiers.recycle_buffers_range(&header);
+    assert_eq!(tiers.small().tail(), small_tail.wrapping_add(2));
+    assert_eq!(tiers.large().tail(), large_tail);
+    assert_eq!(
+        (tiers.small().in_flight(), tiers.large().in_flight()),
+        (0, 1)
+    );
+
+    tiers.recycle_buffers_range(&body);
+    assert_eq!(tiers.large().tail(), large_tail.wrapping_add(1));
+    assert_eq!(tiers.large().in_flight(), 0);
+}
+
+#[test]
+fn test_tiered_rejects_oversized_completions() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let tiers = Tiers::new(&ring, 0, 1, 2).unwrap();
+
+    // more than the whole small pool, and more than the whole large pool
+    assert!(tiers.get_buffers_range(1, 0, 64 * 16 + 1).is_none());
+    assert!(tiers.get_buffers_range(2, 0, 4096 * 4 + 1).is_none());
+    assert!(tiers.get_buffers_range(1, 16, 1).is_none());
+    assert_eq!(
+        (tiers.small().in_flight(), tiers.large().in_flight()),
+        (0, 0)
+    );
+}
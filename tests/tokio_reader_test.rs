@@ -0,0 +1,83 @@
+#![cfg(feature = "tokio")]
+
+use std::{
+    future::poll_fn,
+    io::Write,
+    os::{fd::AsRawFd, unix::net::UnixStream},
+    pin::Pin,
+    time::Duration,
+};
+
+use io_uring_rb::{RingBuffer, tokio_reader::RingBufferReader};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// reads at most `len` bytes, an empty Vec is EOF
+async fn read<R: AsyncRead + Unpin>(reader: &mut R, len: usize) -> Vec<u8> {
+    let mut storage = vec![0; len];
+    let mut buf = ReadBuf::new(&mut storage);
+    poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf))
+        .await
+        .unwrap();
+    buf.filled().to_vec()
+}
+
+/// runs `f` on a current thread runtime, a reader that stops making progress fails the test
+fn block_on<F: std::future::Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .enable_time()
+        .build()
+        .unwrap()
+        .block_on(async {
+            tokio::time::timeout(Duration::from_secs(10), f)
+                .await
+                .expect("reader stalled")
+        })
+}
+
+#[test]
+fn test_reader_reads_a_socketpair_until_eof() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let br = RingBuffer::<16, 4>::new(&ring, 0, 0).unwrap();
+    let (mut writer, socket) = UnixStream::pair().unwrap();
+    let data: Vec<u8> = (0..40).collect();
+    writer.write_all(&data).unwrap();
+    drop(writer);
+
+    block_on(async {
+        let mut reader = RingBufferReader::new(ring, &br, socket.as_raw_fd()).unwrap();
+        let mut received = Vec::new();
+        loop {
+            // smaller than a buffer, so a received buffer is drained over several reads
+            let chunk = read(&mut reader, 5).await;
+            if chunk.is_empty() {
+                break;
+            }
+            received.extend(chunk);
+        }
+        assert_eq!(received, data);
+        assert!(read(&mut reader, 5).await.is_empty());
+        reader.into_inner();
+    });
+    assert_eq!(br.in_flight(), 0);
+}
+
+#[test]
+fn test_reader_arms_on_a_full_submission_queue() {
+    let mut ring = io_uring::IoUring::new(1).unwrap();
+    let br = RingBuffer::<16, 4>::new(&ring, 0, 0).unwrap();
+    // the only SQ slot is taken, arming has to submit it before pushing the recv
+    let nop = io_uring::opcode::Nop::new().build().user_data(1);
+    unsafe { ring.submission().push(&nop).unwrap() };
+    assert!(ring.submission().is_full());
+
+    let (mut writer, socket) = UnixStream::pair().unwrap();
+    writer.write_all(b"hello").unwrap();
+
+    block_on(async {
+        let mut reader = RingBufferReader::new(ring, &br, socket.as_raw_fd()).unwrap();
+        assert_eq!(read(&mut reader, 16).await, b"hello");
+        reader.into_inner();
+    });
+    assert_eq!(br.in_flight(), 0);
+}
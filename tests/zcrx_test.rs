@@ -0,0 +1,30 @@
+use io_uring_rb::zcrx::ZcRingBuffer;
+
+#[test]
+fn test_zcrx_rejects_bad_refill_size() {
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let err = ZcRingBuffer::new(&ring, 1, 0, 1 << 20, 100).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_zcrx_fails_cleanly_without_support() {
+    // a ring without CQE32 and DEFER_TASKRUN, on loopback which has no hardware rx queue
+    let ring = io_uring::IoUring::new(8).unwrap();
+    let err = ZcRingBuffer::new(&ring, 1, 0, 1 << 20, 64).err().unwrap();
+    if !ZcRingBuffer::is_supported(&ring) {
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+}
+
+#[test]
+fn test_zcrx_passes_setup_errors_through() {
+    // the kernel has zcrx, EINVAL then points at the ring missing CQE32 and DEFER_TASKRUN
+    let ring = io_uring::IoUring::new(8).unwrap();
+    if !ZcRingBuffer::is_supported(&ring) {
+        return;
+    }
+    let err = ZcRingBuffer::new(&ring, 1, 0, 1 << 20, 64).err().unwrap();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+    assert_ne!(err.kind(), std::io::ErrorKind::Unsupported);
+}